@@ -0,0 +1,252 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    error::{ParserAppError, ParserAppResult},
+    history::{load_players, load_tags, match_from_row, HistoryDatabase, MatchRecord},
+};
+
+/// Selects stored matches by their annotations. All given criteria have to match.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AnnotationFilter {
+    pub tags: Vec<String>,
+    pub starred: Option<bool>,
+    /// Case-insensitive substring of the notes
+    pub notes: Option<String>,
+}
+
+impl HistoryDatabase {
+    pub fn set_starred(&self, id: i64, starred: bool) -> ParserAppResult<()> {
+        let connection = self.connection()?;
+        ensure_updated(
+            connection.execute(
+                "UPDATE matches SET starred = ?2 WHERE id = ?1",
+                params![id, starred],
+            )?,
+            id,
+        )
+    }
+
+    pub fn set_notes(&self, id: i64, notes: &str) -> ParserAppResult<()> {
+        let connection = self.connection()?;
+        ensure_updated(
+            connection.execute(
+                "UPDATE matches SET notes = ?2 WHERE id = ?1",
+                params![id, notes],
+            )?,
+            id,
+        )
+    }
+
+    /// Replaces all tags of a match. Returns the tags as stored, see `normalize_tag`.
+    pub fn set_tags(&self, id: i64, tags: &[String]) -> ParserAppResult<Vec<String>> {
+        let mut connection = self.connection()?;
+        ensure_match_exists(&connection, id)?;
+
+        let tx = connection.transaction()?;
+        tx.execute("DELETE FROM match_tags WHERE match_id = ?1", [id])?;
+        for tag in tags.iter().filter_map(|tag| normalize_tag(tag)) {
+            tx.execute(
+                "INSERT OR IGNORE INTO match_tags (match_id, tag) VALUES (?1, ?2)",
+                params![id, tag],
+            )?;
+        }
+        tx.commit()?;
+
+        load_tags(&connection, id)
+    }
+
+    /// Every tag in use together with the number of matches carrying it
+    pub fn list_tags(&self) -> ParserAppResult<Vec<(String, usize)>> {
+        let connection = self.connection()?;
+        let mut statement =
+            connection.prepare("SELECT tag, COUNT(*) FROM match_tags GROUP BY tag ORDER BY tag")?;
+
+        let tags = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(tags)
+    }
+
+    /// Matches that fit the filter, most recent first
+    pub fn annotated_matches(
+        &self,
+        filter: &AnnotationFilter,
+    ) -> ParserAppResult<Vec<MatchRecord>> {
+        let tags = filter
+            .tags
+            .iter()
+            .filter_map(|tag| normalize_tag(tag))
+            .collect::<Vec<_>>();
+
+        let connection = self.connection()?;
+        let mut statement = connection.prepare(
+            "SELECT * FROM matches
+            WHERE (?1 IS NULL OR starred = ?1)
+                AND (?2 IS NULL OR instr(lower(notes), lower(?2)) > 0)
+                AND (
+                    SELECT COUNT(*) FROM match_tags
+                    WHERE match_tags.match_id = matches.id
+                        AND match_tags.tag IN (SELECT value FROM json_each(?3))
+                ) = ?4
+            ORDER BY played_at_utc DESC, id DESC",
+        )?;
+
+        let mut matches = statement
+            .query_map(
+                params![
+                    filter.starred,
+                    filter.notes,
+                    serde_json::to_string(&tags)?,
+                    tags.len(),
+                ],
+                match_from_row,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for record in matches.iter_mut() {
+            record.players = load_players(&connection, record.id)?;
+            record.tags = load_tags(&connection, record.id)?;
+        }
+
+        Ok(matches)
+    }
+
+    /// Adds the annotations of `record` to an already stored match, e.g. when the same game shows
+    /// up in an imported archive. Local notes win over imported ones.
+    pub fn merge_annotations(&self, id: i64, record: &MatchRecord) -> ParserAppResult<()> {
+        let mut connection = self.connection()?;
+        let tx = connection.transaction()?;
+
+        tx.execute(
+            "UPDATE matches SET
+                starred = starred OR ?2,
+                notes = CASE WHEN notes = '' THEN ?3 ELSE notes END
+            WHERE id = ?1",
+            params![id, record.starred, record.notes],
+        )?;
+        for tag in record.tags.iter().filter_map(|tag| normalize_tag(tag)) {
+            tx.execute(
+                "INSERT OR IGNORE INTO match_tags (match_id, tag) VALUES (?1, ?2)",
+                params![id, tag],
+            )?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+}
+
+/// Tags are compared case-insensitively, so "Scrim" and "scrim " are the same tag
+pub(crate) fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().to_lowercase();
+    (!tag.is_empty()).then_some(tag)
+}
+
+fn ensure_match_exists(connection: &Connection, id: i64) -> ParserAppResult<()> {
+    let count: usize =
+        connection.query_row("SELECT COUNT(*) FROM matches WHERE id = ?1", [id], |row| {
+            row.get(0)
+        })?;
+
+    ensure_updated(count, id)
+}
+
+fn ensure_updated(rows: usize, id: i64) -> ParserAppResult<()> {
+    match rows {
+        0 => Err(ParserAppError::NotFoundError(format!(
+            "match {id} in history"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::test_support::record;
+
+    use super::*;
+
+    #[test]
+    fn annotates_and_filters_matches() {
+        let history = HistoryDatabase::open_in_memory().unwrap();
+        let scrim = history.save_record(&record(1)).unwrap();
+        let cast = history.save_record(&record(2)).unwrap();
+        history.save_record(&record(3)).unwrap();
+
+        history.set_starred(scrim, true).unwrap();
+        history
+            .set_notes(scrim, "Lost the mid VP to a flank")
+            .unwrap();
+        let tags = history
+            .set_tags(scrim, &["Scrim".into(), "bug ".into(), "".into()])
+            .unwrap();
+        assert_eq!(tags, vec!["bug", "scrim"]);
+        history
+            .set_tags(cast, &["cast".into(), "scrim".into()])
+            .unwrap();
+        assert!(history.set_starred(42, true).is_err());
+
+        let relic_ids = |filter: AnnotationFilter| {
+            history
+                .annotated_matches(&filter)
+                .unwrap()
+                .iter()
+                .map(|record| record.relic_match_id)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            relic_ids(AnnotationFilter {
+                tags: vec!["scrim".into()],
+                ..Default::default()
+            }),
+            vec![2, 1]
+        );
+        assert_eq!(
+            relic_ids(AnnotationFilter {
+                tags: vec!["scrim".into(), "BUG".into()],
+                ..Default::default()
+            }),
+            vec![1]
+        );
+        assert_eq!(
+            relic_ids(AnnotationFilter {
+                starred: Some(false),
+                ..Default::default()
+            }),
+            vec![3, 2]
+        );
+        assert_eq!(
+            relic_ids(AnnotationFilter {
+                notes: Some("FLANK".into()),
+                ..Default::default()
+            }),
+            vec![1]
+        );
+        assert_eq!(
+            history.list_tags().unwrap(),
+            vec![("bug".into(), 1), ("cast".into(), 1), ("scrim".into(), 2)]
+        );
+
+        // Saving the game again keeps its annotations
+        history.save_record(&record(1)).unwrap();
+        let stored = history.load_match(scrim).unwrap().unwrap();
+        assert!(stored.starred);
+        assert_eq!(stored.notes, "Lost the mid VP to a flank");
+        assert_eq!(stored.tags, vec!["bug", "scrim"]);
+
+        let imported = history
+            .save_record(&MatchRecord {
+                tags: vec!["Cast ".into(), " ".into()],
+                ..record(4)
+            })
+            .unwrap();
+        assert_eq!(
+            history.load_match(imported).unwrap().unwrap().tags,
+            vec!["cast"]
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use std::{
+    fs::{self, File},
+    io::{Read, Write},
+    path::Path,
+};
+
+use chrono::SecondsFormat;
+use serde::{Deserialize, Serialize};
+use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+use super::{
+    error::{ParserAppError, ParserAppResult},
+    history::{HistoryDatabase, MatchRecord},
+    playback,
+};
+
+const ARCHIVE_VERSION: u32 = 1;
+const HISTORY_ENTRY: &str = "history.json";
+const REPLAY_DIRECTORY: &str = "replays/";
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ArchiveManifest {
+    version: u32,
+    exported_at: String,
+    matches: Vec<MatchRecord>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ArchiveSummary {
+    pub matches: usize,
+    pub duplicates: usize,
+    pub replays: usize,
+}
+
+/// Writes the whole match history into a single zip archive. With a `playback_dir` the replay
+/// copies of all matches, named after `copy_name`, are added as well.
+pub fn export_history(
+    history: &HistoryDatabase,
+    archive_path: &Path,
+    playback_dir: Option<&Path>,
+    copy_name: &str,
+) -> ParserAppResult<ArchiveSummary> {
+    let mut matches = history.list_matches()?;
+    let mut messages = history.messages_by_match()?;
+    for record in matches.iter_mut() {
+        record.messages = messages.remove(&record.id).unwrap_or_default();
+    }
+
+    let mut archive = ZipWriter::new(File::create(archive_path)?);
+    let options = SimpleFileOptions::default();
+    let mut summary = ArchiveSummary {
+        matches: matches.len(),
+        ..Default::default()
+    };
+
+    if let Some(playback_dir) = playback_dir {
+        for record in matches.iter() {
+            let file_name =
+                playback::replay_copy_name(copy_name, record.relic_match_id, &record.map);
+            let replay_path = playback_dir.join(&file_name);
+            if !replay_path.exists() {
+                continue;
+            }
+
+            archive.start_file(format!("{REPLAY_DIRECTORY}{file_name}"), options)?;
+            archive.write_all(&fs::read(replay_path)?)?;
+            summary.replays += 1;
+        }
+    }
+
+    let manifest = ArchiveManifest {
+        version: ARCHIVE_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        matches,
+    };
+
+    archive.start_file(HISTORY_ENTRY, options)?;
+    archive.write_all(&serde_json::to_vec(&manifest)?)?;
+    archive.finish()?;
+
+    tracing::info!("Exported match history to {archive_path:?}: {summary:?}");
+    Ok(summary)
+}
+
+/// Merges an archive created by `export_history` into the local history. Matches that are
+/// already known by relic match id or replay md5 are skipped apart from their stars, notes and
+/// tags, replays are only extracted into `playback_dir` if no file of the same name exists.
+pub fn import_history(
+    history: &HistoryDatabase,
+    archive_path: &Path,
+    playback_dir: Option<&Path>,
+) -> ParserAppResult<ArchiveSummary> {
+    let mut archive = ZipArchive::new(File::open(archive_path)?)?;
+
+    let manifest: ArchiveManifest = {
+        let mut entry = archive.by_name(HISTORY_ENTRY)?;
+        let mut content = vec![];
+        entry.read_to_end(&mut content)?;
+        serde_json::from_slice(&content)?
+    };
+
+    if manifest.version > ARCHIVE_VERSION {
+        return Err(ParserAppError::GenericError(format!(
+            "Archive version {} is newer than the supported version {ARCHIVE_VERSION}",
+            manifest.version
+        )));
+    }
+
+    let mut summary = ArchiveSummary::default();
+    for record in manifest.matches.iter() {
+        if let Some(id) = history.find_duplicate(record)? {
+            history.merge_annotations(id, record)?;
+            summary.duplicates += 1;
+            continue;
+        }
+
+        history.save_record(record)?;
+        summary.matches += 1;
+    }
+
+    if let Some(playback_dir) = playback_dir {
+        fs::create_dir_all(playback_dir)?;
+
+        for index in 0..archive.len() {
+            let mut entry = archive.by_index(index)?;
+            let Some(file_name) = entry
+                .name()
+                .strip_prefix(REPLAY_DIRECTORY)
+                .map(String::from)
+            else {
+                continue;
+            };
+
+            // Never write outside of the playback folder, whatever the archive claims
+            let Some(file_name) = Path::new(&file_name)
+                .file_name()
+                .map(|name| name.to_owned())
+            else {
+                continue;
+            };
+
+            let replay_path = playback_dir.join(file_name);
+            if replay_path.exists() {
+                continue;
+            }
+
+            let mut content = vec![];
+            entry.read_to_end(&mut content)?;
+            fs::write(replay_path, content)?;
+            summary.replays += 1;
+        }
+    }
+
+    tracing::info!("Imported match history from {archive_path:?}: {summary:?}");
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{
+        history::MessageRecord,
+        playback::DEFAULT_REPLAY_COPY_NAME,
+        test_support::{record, TestDir},
+    };
+
+    use super::*;
+
+    #[test]
+    fn merges_exported_history() {
+        let test_dir = TestDir::new("archive");
+        let export_playback_dir = test_dir.join("export");
+        let import_playback_dir = test_dir.join("import");
+        fs::create_dir_all(&export_playback_dir).unwrap();
+        fs::write(export_playback_dir.join("1_6p_estia.rec"), b"replay").unwrap();
+
+        let teammate = HistoryDatabase::open_in_memory().unwrap();
+        teammate
+            .save_record(&MatchRecord {
+                starred: true,
+                notes: "Casted on stream".into(),
+                tags: vec!["cast".into()],
+                ..record(1)
+            })
+            .unwrap();
+        teammate.save_record(&record(2)).unwrap();
+        teammate
+            .save_record(&MatchRecord {
+                messages: vec![MessageRecord {
+                    body: "gg".into(),
+                    ..Default::default()
+                }],
+                ..record(3)
+            })
+            .unwrap();
+
+        let archive_path = test_dir.join("history.zip");
+        let exported = export_history(
+            &teammate,
+            &archive_path,
+            Some(&export_playback_dir),
+            DEFAULT_REPLAY_COPY_NAME,
+        )
+        .unwrap();
+        assert_eq!(exported.matches, 3);
+        assert_eq!(exported.replays, 1);
+
+        // Same game by relic id and by replay md5
+        let history = HistoryDatabase::open_in_memory().unwrap();
+        let local_id = history
+            .save_record(&MatchRecord {
+                md5: "local".into(),
+                ..record(1)
+            })
+            .unwrap();
+        history
+            .save_record(&MatchRecord {
+                md5: "md5-2".into(),
+                ..record(0)
+            })
+            .unwrap();
+
+        let imported = import_history(&history, &archive_path, Some(&import_playback_dir)).unwrap();
+        assert_eq!(
+            imported,
+            ArchiveSummary {
+                matches: 1,
+                duplicates: 2,
+                replays: 1,
+            }
+        );
+        assert_eq!(history.list_matches().unwrap().len(), 3);
+        assert!(import_playback_dir.join("1_6p_estia.rec").exists());
+
+        let new_id = history.match_by_relic_id(3).unwrap().unwrap().id;
+        let new = history.load_match(new_id).unwrap().unwrap();
+        assert_eq!(new.messages[0].body, "gg");
+
+        // Annotations of duplicates are merged into the local match
+        let local = history.load_match(local_id).unwrap().unwrap();
+        assert!(local.starred);
+        assert_eq!(local.notes, "Casted on stream");
+        assert_eq!(local.tags, vec!["cast"]);
+    }
+}
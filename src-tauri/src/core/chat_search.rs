@@ -0,0 +1,150 @@
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+use super::{error::ParserAppResult, history::HistoryDatabase};
+
+const DEFAULT_RESULT_LIMIT: usize = 100;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ChatSearchQuery {
+    /// Words that all have to appear in the message body
+    pub text: String,
+    pub sender: Option<String>,
+    /// `All` or `Team`
+    pub receiver: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ChatSearchResult {
+    pub match_id: i64,
+    pub relic_match_id: usize,
+    pub map: String,
+    pub played_at: String,
+    pub tick: usize,
+    pub sender: String,
+    pub receiver: String,
+    pub body: String,
+}
+
+impl HistoryDatabase {
+    /// Full-text search over the chat of every stored match, best matches first
+    pub fn search_messages(
+        &self,
+        query: &ChatSearchQuery,
+    ) -> ParserAppResult<Vec<ChatSearchResult>> {
+        let fts_query = to_fts_query(&query.text);
+        if fts_query.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let connection = self.connection()?;
+        let mut statement = connection.prepare(
+            "SELECT messages.match_id, matches.relic_match_id, matches.map, matches.played_at,
+                messages.tick, messages.sender, messages.receiver, messages.body
+            FROM messages_fts
+            JOIN messages ON messages.id = messages_fts.rowid
+            JOIN matches ON matches.id = messages.match_id
+            WHERE messages_fts MATCH ?1
+                AND (?2 IS NULL OR messages.sender = ?2 COLLATE NOCASE)
+                AND (?3 IS NULL OR messages.receiver = ?3 COLLATE NOCASE)
+            ORDER BY messages_fts.rank
+            LIMIT ?4",
+        )?;
+
+        let results = statement
+            .query_map(
+                params![
+                    fts_query,
+                    query.sender,
+                    query.receiver,
+                    query.limit.unwrap_or(DEFAULT_RESULT_LIMIT),
+                ],
+                |row| {
+                    Ok(ChatSearchResult {
+                        match_id: row.get(0)?,
+                        relic_match_id: row.get(1)?,
+                        map: row.get(2)?,
+                        played_at: row.get(3)?,
+                        tick: row.get(4)?,
+                        sender: row.get(5)?,
+                        receiver: row.get(6)?,
+                        body: row.get(7)?,
+                    })
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(results)
+    }
+}
+
+/// Turns free text into an FTS5 query that matches messages containing all words. Every word is
+/// quoted so that user input can not be interpreted as FTS5 query syntax.
+fn to_fts_query(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::history::{MatchRecord, MessageRecord};
+
+    use super::*;
+
+    fn message(tick: usize, sender: &str, receiver: &str, body: &str) -> MessageRecord {
+        MessageRecord {
+            tick,
+            sender: sender.into(),
+            receiver: receiver.into(),
+            body: body.into(),
+            player_id: 1000,
+        }
+    }
+
+    #[test]
+    fn finds_messages_across_matches() {
+        let history = HistoryDatabase::open_in_memory().unwrap();
+        history
+            .save_record(&MatchRecord {
+                relic_match_id: 1,
+                map: "6p_estia".into(),
+                messages: vec![
+                    message(10, "Alice", "All", "glhf"),
+                    message(20, "Bob", "Team", "push left NOW"),
+                ],
+                ..Default::default()
+            })
+            .unwrap();
+        history
+            .save_record(&MatchRecord {
+                relic_match_id: 2,
+                map: "6p_avensa".into(),
+                messages: vec![message(30, "Alice", "All", "left side is \"open\"")],
+                ..Default::default()
+            })
+            .unwrap();
+
+        let search = |text: &str, sender: Option<&str>, receiver: Option<&str>| {
+            history
+                .search_messages(&ChatSearchQuery {
+                    text: text.into(),
+                    sender: sender.map(Into::into),
+                    receiver: receiver.map(Into::into),
+                    limit: None,
+                })
+                .unwrap()
+        };
+
+        assert_eq!(search("left", None, None).len(), 2);
+        assert_eq!(search("push left", None, None).len(), 1);
+        assert_eq!(search("now", None, None)[0].relic_match_id, 1);
+        assert_eq!(search("left", Some("alice"), None)[0].map, "6p_avensa");
+        assert_eq!(search("left", None, Some("team"))[0].tick, 20);
+        assert_eq!(search("\"open", None, None).len(), 1);
+        assert!(search("   ", None, None).is_empty());
+    }
+}
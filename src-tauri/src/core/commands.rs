@@ -0,0 +1,327 @@
+use std::{fs, path::PathBuf};
+
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, State};
+
+use super::{
+    annotations::AnnotationFilter,
+    archive::{self, ArchiveSummary},
+    chat_search::{ChatSearchQuery, ChatSearchResult},
+    diagnostics::{DiagnosticsBundle, DiagnosticsInfo},
+    dry_run,
+    error::{ParserAppError, ParserAppResult},
+    game::{self, ReportStatus, REPORT_STATUS_EVENT},
+    get_playback_dir,
+    head_to_head::{self, HeadToHead},
+    history::{DateRange, HistoryDatabase, LocalProfile, MatchRecord},
+    logging,
+    quarantine::{self, QuarantineEntry},
+    rating::{PlayerRating, RatingEngine},
+    retention::{self, PruneCandidate},
+    settings::Settings,
+    stats::{self, PlayerStatistics},
+    transport::{HttpReportTransport, ReportTransport},
+    GamePipeline,
+};
+
+/// Sends a report that was previously written to disk during a dry run to the ESL server and
+/// marks the match as sent
+#[tauri::command]
+pub fn submit_dry_run_report(
+    handle: AppHandle,
+    history: State<HistoryDatabase>,
+    match_id: usize,
+) -> ParserAppResult<String> {
+    let settings = Settings::load(&handle)?;
+    let report_path = dry_run::find_report(&settings.dry_run.output_dir(&handle)?, match_id)?;
+
+    let payload: Value = serde_json::from_slice(&fs::read(&report_path)?)?;
+    let transport = HttpReportTransport::new(&settings.report.endpoint, settings.report.timeout())?;
+
+    let response = transport.send_report(&payload)?;
+    tracing::info!("Submitted dry run report for match {match_id}: {response:?}");
+
+    let status = game::response_status(response.clone());
+    if history
+        .record_report_attempt(match_id, &status, true)?
+        .is_none()
+    {
+        tracing::warn!("Submitted match {match_id} is not in the match history");
+    }
+    handle.emit(
+        REPORT_STATUS_EVENT,
+        ReportStatus {
+            id: match_id,
+            status,
+            sent: true,
+        },
+    )?;
+
+    Ok(response)
+}
+
+/// All recorded games, or only those played with the given Steam profile logged in
+#[tauri::command]
+pub fn get_match_history(
+    history: State<HistoryDatabase>,
+    local_steam_id: Option<usize>,
+) -> ParserAppResult<Vec<MatchRecord>> {
+    let mut matches = history.list_matches()?;
+    if let Some(local_steam_id) = local_steam_id {
+        matches.retain(|record| record.local_steam_id == local_steam_id);
+    }
+
+    Ok(matches)
+}
+
+/// Everybody who played on this machine, for picking whose history and statistics to show
+#[tauri::command]
+pub fn get_local_profiles(history: State<HistoryDatabase>) -> ParserAppResult<Vec<LocalProfile>> {
+    history.local_profiles()
+}
+
+#[tauri::command]
+pub fn get_match(history: State<HistoryDatabase>, id: i64) -> ParserAppResult<Option<MatchRecord>> {
+    history.load_match(id)
+}
+
+/// Statistics for the given player, or for the local player if no steam id is passed
+#[tauri::command]
+pub fn get_player_statistics(
+    history: State<HistoryDatabase>,
+    steam_id: Option<usize>,
+    range: Option<DateRange>,
+) -> ParserAppResult<PlayerStatistics> {
+    let steam_id = match steam_id {
+        Some(steam_id) => steam_id,
+        None => history.latest_local_steam_id()?.ok_or_else(|| {
+            ParserAppError::NotFoundError("local steam profile in match history".into())
+        })?,
+    };
+
+    let matches = history.matches_with_player(steam_id, &range.unwrap_or_default())?;
+
+    Ok(stats::player_statistics(steam_id, &matches))
+}
+
+/// Shared games of two players, identified by either steam id or relic id
+#[tauri::command]
+pub fn get_head_to_head(
+    history: State<HistoryDatabase>,
+    player_id: u64,
+    opponent_id: u64,
+) -> ParserAppResult<HeadToHead> {
+    let matches = history.matches_with_players(player_id, opponent_id)?;
+
+    head_to_head::head_to_head(player_id, opponent_id, &matches)
+}
+
+/// Local ratings of every player in the match history, best player first
+#[tauri::command]
+pub fn get_ratings(
+    handle: AppHandle,
+    history: State<HistoryDatabase>,
+) -> ParserAppResult<Vec<PlayerRating>> {
+    let settings = Settings::load(&handle)?;
+    let matches = history.finished_matches()?;
+
+    Ok(RatingEngine::replay(settings.rating, &matches))
+}
+
+#[tauri::command]
+pub fn get_player_rating(
+    handle: AppHandle,
+    history: State<HistoryDatabase>,
+    steam_id: usize,
+) -> ParserAppResult<Option<PlayerRating>> {
+    let ratings = get_ratings(handle, history)?;
+
+    Ok(ratings
+        .into_iter()
+        .find(|rating| rating.steam_id == steam_id))
+}
+
+#[tauri::command]
+pub fn search_chat(
+    history: State<HistoryDatabase>,
+    query: ChatSearchQuery,
+) -> ParserAppResult<Vec<ChatSearchResult>> {
+    history.search_messages(&query)
+}
+
+#[tauri::command]
+pub fn export_history(
+    handle: AppHandle,
+    history: State<HistoryDatabase>,
+    archive_path: PathBuf,
+    include_replays: bool,
+) -> ParserAppResult<ArchiveSummary> {
+    let settings = Settings::load(&handle)?;
+    let playback_dir = match include_replays {
+        true => Some(get_playback_dir(&handle, &settings)?),
+        false => None,
+    };
+
+    archive::export_history(
+        &history,
+        &archive_path,
+        playback_dir.as_deref(),
+        &settings.paths.replay_copy_name,
+    )
+}
+
+#[tauri::command]
+pub fn import_history(
+    handle: AppHandle,
+    history: State<HistoryDatabase>,
+    archive_path: PathBuf,
+    include_replays: bool,
+) -> ParserAppResult<ArchiveSummary> {
+    let playback_dir = match include_replays {
+        true => Some(get_playback_dir(&handle, &Settings::load(&handle)?)?),
+        false => None,
+    };
+
+    archive::import_history(&history, &archive_path, playback_dir.as_deref())
+}
+
+/// Lists the archived replays the current retention rules would delete
+#[tauri::command]
+pub fn preview_retention(
+    handle: AppHandle,
+    history: State<HistoryDatabase>,
+) -> ParserAppResult<Vec<PruneCandidate>> {
+    let settings = Settings::load(&handle)?;
+    retention::preview(
+        &settings.retention,
+        &history,
+        &get_playback_dir(&handle, &settings)?,
+        &settings.paths.replay_copy_name,
+    )
+}
+
+/// Applies the current retention rules right away, even if the background pruner is disabled
+#[tauri::command]
+pub fn apply_retention(
+    handle: AppHandle,
+    history: State<HistoryDatabase>,
+) -> ParserAppResult<Vec<PruneCandidate>> {
+    let settings = Settings::load(&handle)?;
+    retention::prune(
+        &settings.retention,
+        &history,
+        &get_playback_dir(&handle, &settings)?,
+        &settings.paths.replay_copy_name,
+    )
+}
+
+#[tauri::command]
+pub fn set_match_starred(
+    history: State<HistoryDatabase>,
+    id: i64,
+    starred: bool,
+) -> ParserAppResult<()> {
+    history.set_starred(id, starred)
+}
+
+#[tauri::command]
+pub fn set_match_notes(
+    history: State<HistoryDatabase>,
+    id: i64,
+    notes: String,
+) -> ParserAppResult<()> {
+    history.set_notes(id, &notes)
+}
+
+#[tauri::command]
+pub fn set_match_tags(
+    history: State<HistoryDatabase>,
+    id: i64,
+    tags: Vec<String>,
+) -> ParserAppResult<Vec<String>> {
+    history.set_tags(id, &tags)
+}
+
+/// All tags in use with the number of matches per tag
+#[tauri::command]
+pub fn get_tags(history: State<HistoryDatabase>) -> ParserAppResult<Vec<(String, usize)>> {
+    history.list_tags()
+}
+
+#[tauri::command]
+pub fn get_annotated_matches(
+    history: State<HistoryDatabase>,
+    filter: AnnotationFilter,
+) -> ParserAppResult<Vec<MatchRecord>> {
+    history.annotated_matches(&filter)
+}
+
+/// Tail of the app log for the in-app log viewer, oldest line first
+#[tauri::command]
+pub fn get_recent_log_lines(
+    handle: AppHandle,
+    limit: Option<usize>,
+) -> ParserAppResult<Vec<String>> {
+    logging::recent_log_lines(&logging::get_log_dir(&handle)?, limit)
+}
+
+/// Zips everything needed to look into a parsing problem into `bundle_path`
+#[tauri::command]
+pub fn create_diagnostics_bundle(
+    handle: AppHandle,
+    bundle_path: PathBuf,
+) -> ParserAppResult<DiagnosticsInfo> {
+    let bundle = DiagnosticsBundle::collect(&handle)?;
+    bundle.write(&bundle_path)?;
+
+    Ok(bundle.info)
+}
+
+#[tauri::command]
+pub fn get_quarantined_games(handle: AppHandle) -> ParserAppResult<Vec<QuarantineEntry>> {
+    quarantine::list(&quarantine::get_quarantine_dir(&handle)?)
+}
+
+/// Runs a quarantined game through the pipeline again, e.g. after a parser update. On success the
+/// game is reported like a new one and leaves the quarantine, returning its relic match id.
+#[tauri::command]
+pub fn reprocess_quarantined_game(handle: AppHandle, id: String) -> ParserAppResult<usize> {
+    let quarantine_dir = quarantine::get_quarantine_dir(&handle)?;
+    let (logfile_path, replay_file_path) = quarantine::input_files(&quarantine_dir, &id)?;
+    quarantine::load(&quarantine_dir, &id)?;
+
+    let pipeline = GamePipeline::new(&handle)?;
+    match pipeline.process(&handle, &logfile_path, &replay_file_path) {
+        Ok(game) => {
+            quarantine::remove(&quarantine_dir, &id)?;
+            tracing::info!("Rescued quarantined game {id} as match {}", game.id);
+
+            Ok(game.id)
+        }
+        Err(e) => {
+            quarantine::record_failed_attempt(&quarantine_dir, &id, &e)?;
+
+            Err(e)
+        }
+    }
+}
+
+#[tauri::command]
+pub fn delete_quarantined_game(handle: AppHandle, id: String) -> ParserAppResult<()> {
+    quarantine::remove(&quarantine::get_quarantine_dir(&handle)?, &id)
+}
+
+#[tauri::command]
+pub fn get_settings(handle: AppHandle) -> ParserAppResult<Settings> {
+    Settings::load(&handle)
+}
+
+/// Validates and stores the settings. The running watcher picks them up right away and the
+/// retention rules apply from the next hourly run of the pruner. `logging.level` and
+/// `logging.max_files` are only read when the app starts and need a restart.
+#[tauri::command]
+pub fn set_settings(handle: AppHandle, settings: Settings) -> ParserAppResult<Settings> {
+    settings.save(&handle)?;
+
+    Settings::load(&handle)
+}
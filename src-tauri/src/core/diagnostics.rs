@@ -0,0 +1,307 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::SecondsFormat;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+use super::{
+    error::ParserAppResult,
+    get_logfile_path, get_playback_dir,
+    logfile::{LogfileGameList, ParseDiagnostic},
+    logging::{get_log_dir, log_files},
+    parse_logfile,
+    settings::Settings,
+};
+
+const INFO_ENTRY: &str = "info.json";
+const SETTINGS_ENTRY: &str = "settings.json";
+const LOGFILE_EXCERPT_ENTRY: &str = "warnings_excerpt.txt";
+const REPLAY_ENTRY: &str = "temp.rec";
+const LOG_DIRECTORY: &str = "logs/";
+/// Settings keys whose values must never leave the machine
+const SECRET_KEYS: [&str; 2] = ["webhook_url", "steam_id_hash_key"];
+const REDACTED: &str = "[redacted]";
+
+lazy_static! {
+    /// User name in the header of the logfile
+    static ref USER_LINE_REGEXP: Regex = Regex::new(r"(?m)(USER: )[^\r\n]*").unwrap();
+    /// Account name segment of paths below a home directory, on Windows and elsewhere
+    static ref HOME_DIR_REGEXP: Regex =
+        Regex::new(r#"(?i)([a-z]:[\\/]+(?:users|documents and settings)[\\/]+|/home/|/users/)[^\\/\s'"]+"#)
+            .unwrap();
+    static ref STEAM_ID_REGEXP: Regex = Regex::new(r"\b7656119\d{10}\b").unwrap();
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct DiagnosticsInfo {
+    pub app_version: String,
+    pub os: String,
+    pub created_at: String,
+    pub paths: Vec<DiagnosticsPath>,
+    /// Outcome of parsing the logfile right now, `None` if it parsed fine
+    pub logfile_error: Option<String>,
+    /// Lines lenient parsing had to skip
+    pub logfile_diagnostics: Vec<ParseDiagnostic>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiagnosticsPath {
+    pub name: String,
+    pub path: PathBuf,
+    pub exists: bool,
+}
+
+impl DiagnosticsPath {
+    fn new(name: &str, path: PathBuf) -> Self {
+        Self {
+            name: name.into(),
+            exists: path.exists(),
+            path,
+        }
+    }
+}
+
+/// Everything we ask for when a player reports a parsing problem
+#[derive(Debug, Default)]
+pub struct DiagnosticsBundle {
+    pub info: DiagnosticsInfo,
+    pub settings: Value,
+    pub logfile_excerpt: Vec<String>,
+    pub replay_path: Option<PathBuf>,
+    pub log_files: Vec<PathBuf>,
+}
+
+impl DiagnosticsBundle {
+    /// Gathers the bundle. Missing files are noted in `info` instead of failing, after all the
+    /// bundle is meant for setups that do not work.
+    pub fn collect(handle: &AppHandle) -> ParserAppResult<Self> {
+        let loaded_settings = Settings::load(handle);
+        // Broken settings are part of the report, look for the inputs in the default places
+        let path_settings = loaded_settings.as_ref().cloned().unwrap_or_default();
+        let logfile_path = get_logfile_path(handle, &path_settings)?;
+        let replay_path = get_playback_dir(handle, &path_settings)?.join("temp.rec");
+        let app_data_dir = handle.path().app_data_dir()?;
+        let log_dir = get_log_dir(handle)?;
+
+        let logfile_error = parse_logfile(&logfile_path).err().map(|e| e.to_string());
+        let logfile_diagnostics = logfile_diagnostics(&logfile_path);
+        let logfile_excerpt = match LogfileGameList::last_game_block(&logfile_path) {
+            Ok(excerpt) => excerpt,
+            Err(e) => vec![format!("Could not read logfile: {e}")],
+        };
+
+        let settings = match loaded_settings {
+            Ok(settings) => redacted_settings(&settings)?,
+            Err(e) => Value::String(format!("Could not load settings: {e}")),
+        };
+
+        let info = DiagnosticsInfo {
+            app_version: handle.package_info().version.to_string(),
+            os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            created_at: chrono::Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            paths: vec![
+                DiagnosticsPath::new("logfile", logfile_path),
+                DiagnosticsPath::new("replay", replay_path.clone()),
+                DiagnosticsPath::new("app_data_dir", app_data_dir),
+                DiagnosticsPath::new("log_dir", log_dir.clone()),
+            ],
+            logfile_error,
+            logfile_diagnostics,
+        };
+
+        Ok(Self {
+            info,
+            settings,
+            logfile_excerpt,
+            replay_path: replay_path.exists().then_some(replay_path),
+            log_files: log_files(&log_dir)?,
+        })
+    }
+
+    pub fn write(&self, bundle_path: &Path) -> ParserAppResult<()> {
+        let mut archive = ZipWriter::new(File::create(bundle_path)?);
+        let options = SimpleFileOptions::default();
+
+        archive.start_file(INFO_ENTRY, options)?;
+        archive.write_all(redact_text(&serde_json::to_string_pretty(&self.info)?).as_bytes())?;
+
+        archive.start_file(SETTINGS_ENTRY, options)?;
+        archive
+            .write_all(redact_text(&serde_json::to_string_pretty(&self.settings)?).as_bytes())?;
+
+        archive.start_file(LOGFILE_EXCERPT_ENTRY, options)?;
+        archive.write_all(redact_text(&self.logfile_excerpt.join("\n")).as_bytes())?;
+
+        if let Some(replay_path) = &self.replay_path {
+            archive.start_file(REPLAY_ENTRY, options)?;
+            archive.write_all(&fs::read(replay_path)?)?;
+        }
+
+        for log_file in self.log_files.iter() {
+            let Some(file_name) = log_file.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+
+            archive.start_file(format!("{LOG_DIRECTORY}{file_name}"), options)?;
+            let log = fs::read(log_file)?;
+            archive.write_all(redact_text(&String::from_utf8_lossy(&log)).as_bytes())?;
+        }
+
+        archive.finish()?;
+
+        tracing::info!("Wrote diagnostics bundle to {bundle_path:?}");
+        Ok(())
+    }
+}
+
+fn logfile_diagnostics(logfile_path: &Path) -> Vec<ParseDiagnostic> {
+    let mut game_list = LogfileGameList::new();
+    match game_list
+        .read_logfile(logfile_path)
+        .and_then(|_| game_list.parse_lenient())
+    {
+        Ok(_) => game_list.diagnostics,
+        Err(_) => vec![],
+    }
+}
+
+/// The settings as JSON with all secrets replaced
+pub fn redacted_settings(settings: &Settings) -> ParserAppResult<Value> {
+    let mut value = serde_json::to_value(settings)?;
+    redact(&mut value);
+
+    Ok(value)
+}
+
+/// Removes what identifies the player from logfile and log lines: the account name of the home
+/// directory, the `USER:` header and steam ids
+pub fn redact_text(text: &str) -> String {
+    let text = USER_LINE_REGEXP.replace_all(text, format!("${{1}}{REDACTED}"));
+    let text = HOME_DIR_REGEXP.replace_all(&text, format!("${{1}}{REDACTED}"));
+
+    STEAM_ID_REGEXP.replace_all(&text, REDACTED).into_owned()
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if SECRET_KEYS.contains(&key.as_str()) && !value.is_null() {
+                    *value = Value::String(REDACTED.into());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use zip::ZipArchive;
+
+    use crate::core::{discord::DiscordWebhookSink, settings::PathSettings, test_support::TestDir};
+
+    use super::*;
+
+    #[test]
+    fn bundles_excerpt_replay_and_redacted_settings() {
+        let test_dir = TestDir::new("diagnostics");
+        fs::write(test_dir.join("replay-parser.2022-11-16.log"), "log line").unwrap();
+
+        let settings = Settings {
+            discord_webhooks: vec![DiscordWebhookSink {
+                webhook_url: "https://discord.com/api/webhooks/1/secret".into(),
+                ..Default::default()
+            }],
+            paths: PathSettings {
+                logfile: Some(PathBuf::from(
+                    "C:\\Users\\Mark\\Documents\\My Games\\Dawn of War II - Retribution\\warnings.txt",
+                )),
+                playback_dir: Some(PathBuf::from("/home/mark/playback")),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let bundle = DiagnosticsBundle {
+            settings: redacted_settings(&settings).unwrap(),
+            logfile_excerpt: LogfileGameList::last_game_block(Path::new("warnings.txt")).unwrap(),
+            replay_path: Some(PathBuf::from("3v3.rec")),
+            log_files: vec![test_dir.join("replay-parser.2022-11-16.log")],
+            ..Default::default()
+        };
+
+        let bundle_path = test_dir.join("diagnostics.zip");
+        bundle.write(&bundle_path).unwrap();
+
+        let mut archive = ZipArchive::new(File::open(&bundle_path).unwrap()).unwrap();
+        let mut entry_names = archive.file_names().map(String::from).collect::<Vec<_>>();
+        entry_names.sort();
+        assert_eq!(
+            entry_names,
+            vec![
+                "info.json",
+                "logs/replay-parser.2022-11-16.log",
+                "settings.json",
+                "temp.rec",
+                "warnings_excerpt.txt",
+            ]
+        );
+
+        let mut settings = String::new();
+        archive
+            .by_name(SETTINGS_ENTRY)
+            .unwrap()
+            .read_to_string(&mut settings)
+            .unwrap();
+        assert!(!settings.contains("secret"));
+        assert!(!settings.contains("Mark"));
+        assert!(!settings.contains("mark"));
+        assert!(settings.contains(REDACTED));
+
+        let mut excerpt = String::new();
+        archive
+            .by_name(LOGFILE_EXCERPT_ENTRY)
+            .unwrap()
+            .read_to_string(&mut excerpt)
+            .unwrap();
+        assert!(!STEAM_ID_REGEXP.is_match(&excerpt));
+    }
+
+    #[test]
+    fn redacts_user_home_dirs_and_steam_ids() {
+        let text = [
+            "10:25:52.98    USER: 'Mark'",
+            "10:26:06.72    Found profile: /steam/76561198099396483",
+            "10:26:07.01    Loading C:\\Users\\Mark\\Documents\\My Games\\Dawn of War II - Retribution",
+            "Playback: /home/mark/.local/share/dow2/playback/temp.rec",
+            "10:25:52.98    E:\\Games\\SteamLibrary\\steamapps\\common\\DOW2.exe",
+        ]
+        .join("\n");
+
+        assert_eq!(
+            redact_text(&text),
+            [
+                "10:25:52.98    USER: [redacted]",
+                "10:26:06.72    Found profile: /steam/[redacted]",
+                "10:26:07.01    Loading C:\\Users\\[redacted]\\Documents\\My Games\\Dawn of War II - Retribution",
+                "Playback: /home/[redacted]/.local/share/dow2/playback/temp.rec",
+                "10:25:52.98    E:\\Games\\SteamLibrary\\steamapps\\common\\DOW2.exe",
+            ]
+            .join("\n")
+        );
+    }
+}
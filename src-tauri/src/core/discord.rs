@@ -0,0 +1,247 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use reqwest::blocking::Client;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{
+    error::{ParserAppError, ParserAppResult},
+    game::ExtendedGameInformation,
+    player_info::{hero_name, race_name, ExtendedPlayerInformation, LogfilePlayerStatus},
+};
+
+/// Posts an embed for every processed match to a Discord webhook
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DiscordWebhookSink {
+    pub webhook_url: String,
+    /// Optional link target of the embed title. `{id}` is replaced with the relic match id.
+    pub match_url: Option<String>,
+    pub only_my_games: bool,
+    /// Only post games with this many players per team, e.g. `3` for 3v3 games
+    pub team_size: Option<usize>,
+    pub only_wins: bool,
+}
+
+impl DiscordWebhookSink {
+    pub fn accepts(&self, game: &ExtendedGameInformation) -> bool {
+        let local_player = game.local_player();
+
+        if self.only_my_games && local_player.is_none() {
+            return false;
+        }
+
+        if self.only_wins
+            && !local_player.is_some_and(|player| player.status == LogfilePlayerStatus::Won)
+        {
+            return false;
+        }
+
+        if let Some(team_size) = self.team_size {
+            let teams = teams(game);
+            if teams.len() < 2 || teams.values().any(|team| team.len() != team_size) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    pub fn publish(&self, client: &Client, game: &ExtendedGameInformation) -> ParserAppResult<()> {
+        let response = client
+            .post(&self.webhook_url)
+            .json(&self.embed(game))
+            .send()?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ParserAppError::HttpStatusError {
+                service: "Discord webhook".into(),
+                status: status.as_u16(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn embed(&self, game: &ExtendedGameInformation) -> Value {
+        let winner = game
+            .players
+            .iter()
+            .find(|player| player.status == LogfilePlayerStatus::Won)
+            .map(|player| format!("Team {}", player.team + 1))
+            .unwrap_or_else(|| "None".into());
+
+        let fields = teams(game)
+            .into_iter()
+            .map(|(team, players)| {
+                let lineup = players
+                    .iter()
+                    .map(|player| {
+                        format!(
+                            "{} ({}, {})",
+                            player.name,
+                            race_name(player.race),
+                            hero_name(player.race, player.hero)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                json!({ "name": format!("Team {}", team + 1), "value": lineup, "inline": true })
+            })
+            .collect::<Vec<_>>();
+
+        let total_seconds = game.ticks / 10;
+
+        let mut embed = json!({
+            "title": game.map_name(),
+            "description": format!(
+                "Winner: {winner}\nDuration: {:02}:{:02}",
+                total_seconds / 60,
+                total_seconds % 60
+            ),
+            "fields": fields,
+        });
+        if let Some(match_url) = &self.match_url {
+            embed["url"] = match_url.replace("{id}", &game.id.to_string()).into();
+        }
+
+        json!({ "embeds": [embed] })
+    }
+}
+
+/// Posts the game to every sink whose filters accept it. Failing sinks do not stop the pipeline,
+/// and no sink holds it up for longer than `timeout`.
+pub fn publish_to_sinks(
+    sinks: &[DiscordWebhookSink],
+    game: &ExtendedGameInformation,
+    timeout: Duration,
+) {
+    let sinks = sinks
+        .iter()
+        .filter(|sink| sink.accepts(game))
+        .collect::<Vec<_>>();
+    if sinks.is_empty() {
+        return;
+    }
+
+    let client = match Client::builder().timeout(timeout).build() {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!("Could not set up the Discord client: {e}");
+            return;
+        }
+    };
+
+    for sink in sinks {
+        if let Err(e) = sink.publish(&client, game) {
+            tracing::error!("Could not post match {} to Discord: {e}", game.id);
+        }
+    }
+}
+
+fn teams(game: &ExtendedGameInformation) -> BTreeMap<u32, Vec<&ExtendedPlayerInformation>> {
+    let mut teams: BTreeMap<u32, Vec<&ExtendedPlayerInformation>> = BTreeMap::new();
+    for player in game.players.iter() {
+        teams.entry(player.team).or_default().push(player);
+    }
+
+    teams
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{
+        mock_esl_server::{MockEslServer, MockResponse},
+        transport::REPORT_TIMEOUT,
+    };
+
+    use super::*;
+
+    fn player(
+        steam_id: usize,
+        team: u32,
+        status: LogfilePlayerStatus,
+    ) -> ExtendedPlayerInformation {
+        ExtendedPlayerInformation {
+            name: format!("Player {steam_id}"),
+            steam_id,
+            team,
+            race: 2,
+            status,
+            ..Default::default()
+        }
+    }
+
+    fn game() -> ExtendedGameInformation {
+        let mut game = ExtendedGameInformation::new();
+        game.id = 54864600;
+        game.ticks = 6600;
+        game.local_steam_id = 1;
+        game.map.path = "DATA:maps\\pvp\\2p_calderisdunes".into();
+        game.players = vec![
+            player(1, 0, LogfilePlayerStatus::Conceded),
+            player(2, 1, LogfilePlayerStatus::Won),
+        ];
+        game
+    }
+
+    #[test]
+    fn filters_games() {
+        let game = game();
+
+        assert!(DiscordWebhookSink::default().accepts(&game));
+        assert!(DiscordWebhookSink {
+            only_my_games: true,
+            ..Default::default()
+        }
+        .accepts(&game));
+        assert!(!DiscordWebhookSink {
+            only_wins: true,
+            ..Default::default()
+        }
+        .accepts(&game));
+        assert!(!DiscordWebhookSink {
+            team_size: Some(3),
+            ..Default::default()
+        }
+        .accepts(&game));
+    }
+
+    #[test]
+    fn posts_embed_to_webhook() {
+        let server = MockEslServer::start(MockResponse::Accept);
+        let sink = DiscordWebhookSink {
+            webhook_url: server.url("/api/webhooks/1/token"),
+            match_url: Some("https://example.com/matches/{id}".into()),
+            ..Default::default()
+        };
+
+        publish_to_sinks(&[sink], &game(), REPORT_TIMEOUT);
+
+        let received = server.received_reports();
+        assert_eq!(received.len(), 1);
+
+        let embed = &received[0]["embeds"][0];
+        assert_eq!(embed["title"], "2p_calderisdunes");
+        assert_eq!(embed["url"], "https://example.com/matches/54864600");
+        assert_eq!(embed["description"], "Winner: Team 2\nDuration: 11:00");
+        assert_eq!(embed["fields"][0]["value"], "Player 1 (Orks, Warboss)");
+    }
+
+    #[test]
+    fn slow_webhooks_do_not_hold_up_the_pipeline() {
+        let server = MockEslServer::start(MockResponse::Timeout(Duration::from_secs(2)));
+        let sink = DiscordWebhookSink {
+            webhook_url: server.url("/api/webhooks/1/token"),
+            ..Default::default()
+        };
+
+        let started_at = std::time::Instant::now();
+        publish_to_sinks(&[sink], &game(), Duration::from_millis(200));
+
+        assert!(started_at.elapsed() < Duration::from_secs(2));
+    }
+}
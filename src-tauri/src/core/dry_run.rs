@@ -0,0 +1,55 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use super::error::{ParserAppError, ParserAppResult};
+
+const DRY_RUN_DIRECTORY: &str = "dry-run";
+
+/// With dry runs enabled reports are written to disk instead of being sent to the ESL server
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DryRunSettings {
+    pub enabled: bool,
+    /// Defaults to a `dry-run` folder in the app data directory
+    pub output_dir: Option<PathBuf>,
+}
+
+impl DryRunSettings {
+    pub fn output_dir(&self, handle: &AppHandle) -> ParserAppResult<PathBuf> {
+        match &self.output_dir {
+            Some(output_dir) => Ok(output_dir.clone()),
+            None => Ok(handle.path().app_data_dir()?.join(DRY_RUN_DIRECTORY)),
+        }
+    }
+}
+
+pub fn report_file_name(match_id: usize, map_name: &str) -> String {
+    format!("{match_id}_{map_name}.json")
+}
+
+/// Looks up the report payload that was written for the given match during a dry run
+pub fn find_report(output_dir: &Path, match_id: usize) -> ParserAppResult<PathBuf> {
+    let prefix = format!("{match_id}_");
+
+    for entry in fs::read_dir(output_dir)? {
+        let path = entry?.path();
+        let is_report = path.extension().is_some_and(|ext| ext == "json");
+        let matches_id = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(&prefix));
+
+        if is_report && matches_id {
+            return Ok(path);
+        }
+    }
+
+    Err(ParserAppError::NotFoundError(format!(
+        "dry run report for match {match_id}"
+    )))
+}
@@ -1,7 +1,12 @@
-use std::{convert::Infallible, string::FromUtf8Error};
+use std::{
+    convert::Infallible,
+    error::Error,
+    path::{Path, PathBuf},
+    string::FromUtf8Error,
+};
 
 use color_eyre::Report;
-use serde::{Serialize, Serializer};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
 
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
@@ -15,71 +20,235 @@ pub enum ParserAppError {
     LogfileParseError(String),
     #[error("Could not find replay file")]
     ReplayNotFoundError,
+    #[error("File system error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Invalid UTF-8: {0}")]
+    Utf8Error(#[from] FromUtf8Error),
+    #[error("File watcher error: {0}")]
+    WatcherError(#[from] notify::Error),
+    #[error("File watcher errors: {}", join_errors(.0))]
+    WatcherErrors(Vec<notify::Error>),
+    #[error("Tauri error: {0}")]
+    TauriError(#[from] tauri::Error),
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("HTTP error: {0}")]
+    HttpError(#[from] reqwest::Error),
+    #[error("{service} responded with {status}")]
+    HttpStatusError { service: String, status: u16 },
+    #[error("History database error: {0}")]
+    DatabaseError(#[from] rusqlite::Error),
+    #[error("Archive error: {0}")]
+    ArchiveError(#[from] zip::result::ZipError),
+    #[error("Not found: {0}")]
+    NotFoundError(String),
+    #[error("Invalid settings: {}", .0.join("; "))]
+    InvalidSettingsError(Vec<String>),
+    #[error("{0}")]
+    EyreError(Report),
     #[error("Generic error: {0}")]
     GenericError(String),
+    /// Any of the above together with where it happened, see `ResultExt`
+    #[error("{error}")]
+    ContextError {
+        error: Box<ParserAppError>,
+        context: ErrorContext,
+    },
 }
 
 pub type ParserAppResult<T, E = ParserAppError> = color_eyre::Result<T, E>;
 
-impl Serialize for ParserAppError {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        serializer.serialize_str(self.to_string().as_ref())
-    }
+/// Where an error happened. Every field is optional, errors collect context on their way up.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ErrorContext {
+    pub path: Option<PathBuf>,
+    pub match_id: Option<usize>,
+    /// Step of the replay pipeline, e.g. `parse_logfile` or `send_report`
+    pub stage: Option<&'static str>,
 }
 
-impl From<std::io::Error> for ParserAppError {
-    fn from(e: std::io::Error) -> Self {
-        ParserAppError::GenericError(e.to_string())
+impl ParserAppError {
+    /// Stable identifier the frontend can match on, unlike the message
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParserAppError::ParserLibError(_) => "parser_lib",
+            ParserAppError::LogfileNotFoundError => "logfile_not_found",
+            ParserAppError::LogfileParseError(_) => "logfile_parse",
+            ParserAppError::ReplayNotFoundError => "replay_not_found",
+            ParserAppError::IoError(_) => "io",
+            ParserAppError::Utf8Error(_) => "utf8",
+            ParserAppError::WatcherError(_) | ParserAppError::WatcherErrors(_) => "watcher",
+            ParserAppError::TauriError(_) => "tauri",
+            ParserAppError::JsonError(_) => "json",
+            ParserAppError::HttpError(_) => "http",
+            ParserAppError::HttpStatusError { .. } => "http_status",
+            ParserAppError::DatabaseError(_) => "database",
+            ParserAppError::ArchiveError(_) => "archive",
+            ParserAppError::NotFoundError(_) => "not_found",
+            ParserAppError::InvalidSettingsError(_) => "invalid_settings",
+            ParserAppError::EyreError(_) => "eyre",
+            ParserAppError::GenericError(_) => "generic",
+            ParserAppError::ContextError { error, .. } => error.code(),
+        }
     }
-}
 
-impl From<FromUtf8Error> for ParserAppError {
-    fn from(e: FromUtf8Error) -> Self {
-        ParserAppError::GenericError(e.to_string())
+    pub fn context(&self) -> ErrorContext {
+        match self {
+            ParserAppError::ContextError { context, .. } => context.clone(),
+            _ => ErrorContext::default(),
+        }
     }
-}
 
-impl From<notify::Error> for ParserAppError {
-    fn from(e: notify::Error) -> Self {
-        ParserAppError::GenericError(e.to_string())
+    /// Messages of the underlying errors, outermost first, without the error itself
+    pub fn sources(&self) -> Vec<String> {
+        if let ParserAppError::ContextError { error, .. } = self {
+            return error.sources();
+        }
+
+        if let ParserAppError::EyreError(report) = self {
+            return report.chain().skip(1).map(|e| e.to_string()).collect();
+        }
+
+        let mut sources = vec![];
+        let mut source = self.source();
+        while let Some(error) = source {
+            sources.push(error.to_string());
+            source = error.source();
+        }
+
+        sources
     }
-}
 
-impl From<tauri::Error> for ParserAppError {
-    fn from(e: tauri::Error) -> Self {
-        ParserAppError::GenericError(e.to_string())
+    /// Adds context to the error, keeping what the error already knew where it is more specific
+    pub fn with_context(self, update: impl FnOnce(&mut ErrorContext)) -> Self {
+        let (error, mut context) = match self {
+            ParserAppError::ContextError { error, context } => (error, context),
+            error => (Box::new(error), ErrorContext::default()),
+        };
+
+        let mut outer = ErrorContext::default();
+        update(&mut outer);
+        context.path = context.path.or(outer.path);
+        context.match_id = context.match_id.or(outer.match_id);
+        context.stage = context.stage.or(outer.stage);
+
+        ParserAppError::ContextError { error, context }
     }
 }
 
-impl From<serde_json::Error> for ParserAppError {
-    fn from(e: serde_json::Error) -> Self {
-        ParserAppError::GenericError(e.to_string())
+/// Attaches `ErrorContext` to any result that converts into a `ParserAppResult`
+pub trait ResultExt<T> {
+    fn with_path(self, path: &Path) -> ParserAppResult<T>;
+    fn with_match_id(self, match_id: usize) -> ParserAppResult<T>;
+    fn in_stage(self, stage: &'static str) -> ParserAppResult<T>;
+}
+
+impl<T, E: Into<ParserAppError>> ResultExt<T> for Result<T, E> {
+    fn with_path(self, path: &Path) -> ParserAppResult<T> {
+        self.map_err(|e| {
+            e.into()
+                .with_context(|context| context.path = Some(path.to_path_buf()))
+        })
+    }
+
+    fn with_match_id(self, match_id: usize) -> ParserAppResult<T> {
+        self.map_err(|e| {
+            e.into()
+                .with_context(|context| context.match_id = Some(match_id))
+        })
+    }
+
+    fn in_stage(self, stage: &'static str) -> ParserAppResult<T> {
+        self.map_err(|e| e.into().with_context(|context| context.stage = Some(stage)))
     }
 }
 
-impl From<reqwest::Error> for ParserAppError {
-    fn from(e: reqwest::Error) -> Self {
-        ParserAppError::GenericError(e.to_string())
+/// Errors reach the frontend as `{ code, message, sources, context }`
+impl Serialize for ParserAppError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut error = serializer.serialize_struct("ParserAppError", 4)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.serialize_field("sources", &self.sources())?;
+        error.serialize_field("context", &self.context())?;
+        error.end()
     }
 }
 
 impl From<Vec<notify::Error>> for ParserAppError {
-    fn from(_: Vec<notify::Error>) -> Self {
-        ParserAppError::GenericError("Notify error".into())
+    fn from(e: Vec<notify::Error>) -> Self {
+        ParserAppError::WatcherErrors(e)
     }
 }
 
 impl From<Infallible> for ParserAppError {
     fn from(e: Infallible) -> Self {
-        ParserAppError::GenericError(e.to_string())
+        match e {}
     }
 }
 
 impl From<Report> for ParserAppError {
     fn from(e: Report) -> Self {
-        ParserAppError::GenericError(e.to_string())
+        ParserAppError::EyreError(e)
+    }
+}
+
+fn join_errors(errors: &[notify::Error]) -> String {
+    errors
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn read_missing_file() -> ParserAppResult<String> {
+        let path = Path::new("does-not-exist.txt");
+        std::fs::read_to_string(path).with_path(path)
+    }
+
+    #[test]
+    fn serializes_code_and_context() {
+        let error = read_missing_file()
+            .in_stage("parse_logfile")
+            .with_match_id(42)
+            .with_path(Path::new("outer.txt"))
+            .unwrap_err();
+
+        assert_eq!(error.code(), "io");
+
+        let serialized = serde_json::to_value(&error).unwrap();
+        assert_eq!(serialized["code"], "io");
+        assert!(serialized["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("File system error"));
+        assert_eq!(
+            serialized["context"],
+            json!({
+                "path": "does-not-exist.txt",
+                "match_id": 42,
+                "stage": "parse_logfile",
+            })
+        );
+    }
+
+    #[test]
+    fn keeps_watcher_errors() {
+        let error = ParserAppError::from(vec![
+            notify::Error::generic("first"),
+            notify::Error::generic("second"),
+        ]);
+
+        assert_eq!(error.code(), "watcher");
+        assert_eq!(error.to_string(), "File watcher errors: first, second");
     }
 }
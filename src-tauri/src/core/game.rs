@@ -26,12 +26,27 @@ use tracing::{error, info};
 use crate::core::error::ParserAppError;
 
 use super::{
-    error::ParserAppResult,
-    logfile::LogfileGameInfo,
+    dry_run,
+    error::{ParserAppResult, ResultExt},
+    league::LeagueRules,
+    logfile::{LeaverEvent, LogfileGameInfo, ParseDiagnostic, SessionInfo},
+    playback,
     player_info::{ExtendedPlayerInformation, LogfilePlayerInfo},
+    privacy::PrivacySettings,
     replay_reporter_dto::ReplayReportDto,
+    transport::ReportTransport,
 };
 
+/// Emitted when the report of a game that is already listed in the UI changes
+pub const REPORT_STATUS_EVENT: &str = "report-status";
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ReportStatus {
+    pub id: usize,
+    pub status: String,
+    pub sent: bool,
+}
+
 #[derive(Debug, Default, Serialize)]
 pub struct ExtendedGameInformation {
     pub id: usize,
@@ -44,12 +59,31 @@ pub struct ExtendedGameInformation {
     pub game: GameInfo,
     pub map: Map,
     pub players: Vec<ExtendedPlayerInformation>,
+    pub local_steam_id: usize,
     pub messages: Vec<Message>,
     pub actions: Vec<Action>,
     pub aborted: bool,
+    pub ranked: bool,
+    pub league: bool,
     pub frames: usize,
+    /// RFC 3339 times from the logfile, empty if the logfile carries no times
+    pub started_at: String,
     pub ended_at: String,
+    /// Seconds between loading into and leaving the game, pauses included
+    pub real_duration_secs: Option<u64>,
+    pub session: SessionInfo,
+    pub humans: usize,
+    pub computers: usize,
+    /// Computer opponents were part of the game, see `LogfileGameInfo::has_computer_players`
+    pub computer_players: bool,
+    pub leaver_timeline: Vec<LeaverEvent>,
+    pub first_leaver: Option<LeaverEvent>,
+    /// Players loaded with differing checksums, i.e. somebody modified their game files
+    pub checksum_mismatch: bool,
+    /// Problems with the game that do not stop it from being reported
+    pub warnings: Vec<String>,
     pub status: String,
+    pub sent: bool,
     pub dev: Option<bool>,
     pub replay: Option<String>,
 }
@@ -137,11 +171,26 @@ impl ExtendedGameInformation {
             .collect::<Vec<_>>();
 
         self.aborted = parsed_logfile_game.aborted;
+        self.ranked = parsed_logfile_game.is_ranked();
         self.id = parsed_logfile_game.id;
         self.map = map.unwrap_or_default();
         self.frames = parsed_logfile_game.frames;
-        self.ended_at = parsed_replay.date.clone();
+        self.started_at = parsed_logfile_game.started_at.clone();
+        self.ended_at = parsed_logfile_game.ended_at.clone();
+        self.real_duration_secs = parsed_logfile_game
+            .real_duration()
+            .and_then(|duration| u64::try_from(duration.num_seconds()).ok());
         self.players = players_with_extended_information;
+        self.local_steam_id = parsed_logfile_game.steam_id;
+        self.mark_local_player();
+        self.session = parsed_logfile_game.session.clone();
+        self.checksum_mismatch = parsed_logfile_game.checksum_mismatch();
+        self.humans = parsed_logfile_game.humans;
+        self.computers = parsed_logfile_game.computers;
+        self.computer_players = parsed_logfile_game.has_computer_players();
+        self.leaver_timeline = parsed_logfile_game.leaver_timeline.clone();
+        self.first_leaver = parsed_logfile_game.first_leaver().cloned();
+        self.note_skipped_lines(&parsed_logfile_game.diagnostics);
         self.messages = parsed_replay.messages;
         self.actions = actions;
         self.name = parsed_replay.name;
@@ -151,6 +200,7 @@ impl ExtendedGameInformation {
         self.date = parsed_replay.date.clone();
         self.ticks = parsed_replay.ticks as usize;
         self.status = "".into();
+        self.sent = false;
         self.dev = None;
         self.replay = None;
         self.game = game;
@@ -158,53 +208,162 @@ impl ExtendedGameInformation {
         self
     }
 
-    pub fn copy_replay_file(&mut self, replay_file_path: &PathBuf) -> ParserAppResult<&mut Self> {
-        let mut file_name = replay_file_path.clone();
-        let map_name = self.map.path.replace("DATA:maps\\pvp\\", "");
+    pub fn map_name(&self) -> String {
+        self.map.path.replace("DATA:maps\\pvp\\", "")
+    }
+
+    /// The player whose steam profile was logged in on this machine, if they took part in the game
+    pub fn local_player(&self) -> Option<&ExtendedPlayerInformation> {
+        self.players
+            .iter()
+            .find(|player| player.steam_id != 0 && player.steam_id == self.local_steam_id)
+    }
 
-        file_name.set_file_name(format!("{}_{}.rec", self.id, map_name));
+    /// Flags the player of `local_steam_id`. Several people may play on the same machine, so this
+    /// is decided per game.
+    pub fn mark_local_player(&mut self) -> &mut Self {
+        let local_steam_id = self.local_steam_id;
+        for player in self.players.iter_mut() {
+            player.local = player.steam_id != 0 && player.steam_id == local_steam_id;
+        }
 
-        let Ok(_) = fs::copy(replay_file_path, file_name) else {
-            return Err(ParserAppError::ParserLibError(
-                "Could not copy replay file".into(),
-            ));
+        self
+    }
+
+    pub fn apply_league_rules(&mut self, league_rules: &LeagueRules) -> &mut Self {
+        self.league = league_rules.is_league_game(self);
+
+        self
+    }
+
+    /// Warns when the client ran another mod version than the ESL server expects. The report is
+    /// still sent, the server decides what to do with it.
+    pub fn check_mod_version(&mut self, expected_mod_version: Option<&str>) -> &mut Self {
+        let Some(expected_mod_version) = expected_mod_version else {
+            tracing::warn!(
+                "Not checking the mod version of game {}, no expected version is set",
+                self.id
+            );
+            return self;
         };
 
+        // Logfiles without a header do not tell the version
+        if self.session.mod_version.is_empty() || self.session.mod_version == expected_mod_version {
+            return self;
+        }
+
+        let warning = format!(
+            "Game was played on mod version {} but the ESL server expects {expected_mod_version}",
+            self.session.mod_version
+        );
+        tracing::warn!("{warning}");
+        self.warnings.push(warning);
+
+        self
+    }
+
+    /// Warns about logfile lines that lenient parsing skipped. Lines with players or results never
+    /// get this far, see `parse_logfile_for_replay`.
+    pub fn note_skipped_lines(&mut self, diagnostics: &[ParseDiagnostic]) -> &mut Self {
+        let Some(diagnostic) = diagnostics.first() else {
+            return self;
+        };
+
+        let warning = format!(
+            "{} lines of the logfile could not be parsed, the first is line {}: {}",
+            diagnostics.len(),
+            diagnostic.line_no,
+            diagnostic.reason
+        );
+        tracing::warn!("{warning}");
+        self.warnings.push(warning);
+
+        self
+    }
+
+    /// Keeps a copy of the replay named after `copy_name` in `playback_dir`, since the game
+    /// overwrites `temp.rec` with the next game
+    pub fn copy_replay_file(
+        &mut self,
+        replay_file_path: &PathBuf,
+        playback_dir: &Path,
+        copy_name: &str,
+    ) -> ParserAppResult<&mut Self> {
+        let file_name = playback_dir.join(playback::replay_copy_name(
+            copy_name,
+            self.id,
+            &self.map_name(),
+        ));
+
+        fs::copy(replay_file_path, &file_name).with_path(&file_name)?;
+
         Ok(self)
     }
 
-    pub fn send_replay_to_server(&mut self) -> ParserAppResult<&mut Self> {
-        let client = reqwest::blocking::Client::new();
-
-        let dto = ReplayReportDto::from(self);
-
-        let request = client
-            .post("http://dawnofwar.info/esl/esl-report.php")
-            .json(&dto)
-            .build()?;
-
-        match client.execute(request) {
-            Ok(response) => {
-                let response_body = response.text();
-                info!("The response message from the server: {:?}", response_body);
-                match response_body {
-                    Ok(body) if body.contains("error") => self.status = body,
-                    Ok(body) if !body.contains("error") => self.status = body,
-                    Ok(_) => self.status = json!({ "response": "ok" }).to_string(),
-                    Err(error) => self.status = error.to_string(),
-                };
+    pub fn send_replay_to_server(
+        &mut self,
+        transport: &dyn ReportTransport,
+        privacy: &PrivacySettings,
+    ) -> ParserAppResult<&mut Self> {
+        let payload = serde_json::to_value(ReplayReportDto::from(self, privacy))?;
 
+        match transport.send_report(&payload) {
+            Ok(body) => {
+                info!("The response message from the server: {:?}", body);
+                self.status = response_status(body);
+
+                self.sent = true;
                 self.replay = None;
             }
             Err(err) => {
                 error!("{:?}", err.to_string());
-                self.status = json!({ "error": err.to_string()}).to_string();
+                self.status = json!({ "error": err.to_string(), "code": err.code() }).to_string();
             }
         }
 
         Ok(self)
     }
 
+    /// Keeps the report to ourselves, e.g. for games against the AI that the ladder does not rate
+    pub fn skip_report(&mut self, reason: &str) -> &mut Self {
+        info!("Not reporting game {}: {reason}", self.id);
+
+        self.status = json!({ "response": "not sent", "reason": reason }).to_string();
+        self.sent = false;
+        self.replay = None;
+
+        self
+    }
+
+    /// Dry run counterpart to `send_replay_to_server`. Writes the exact report payload and a copy
+    /// of the replay to `output_dir` so the report can be inspected and submitted later on.
+    pub fn write_report_to_disk(
+        &mut self,
+        output_dir: &Path,
+        replay_file_path: &PathBuf,
+        copy_name: &str,
+        privacy: &PrivacySettings,
+    ) -> ParserAppResult<&mut Self> {
+        let map_name = self.map_name();
+        let report_path = output_dir.join(dry_run::report_file_name(self.id, &map_name));
+        let replay_path =
+            output_dir.join(playback::replay_copy_name(copy_name, self.id, &map_name));
+
+        let payload = serde_json::to_vec(&ReplayReportDto::from(self, privacy))?;
+
+        fs::create_dir_all(output_dir)?;
+        fs::write(&report_path, payload)?;
+        fs::copy(replay_file_path, &replay_path)?;
+
+        info!("Dry run - wrote report to {:?}", report_path);
+
+        self.status = json!({ "response": "not sent", "report": report_path }).to_string();
+        self.sent = false;
+        self.replay = None;
+
+        Ok(self)
+    }
+
     pub fn transform_replay_to_base64(
         &mut self,
         replay_file_path: &PathBuf,
@@ -225,31 +384,40 @@ impl ExtendedGameInformation {
     }
 }
 
+/// Status of a game whose report the ESL server accepted with the given response body
+pub fn response_status(body: String) -> String {
+    if body.trim().is_empty() {
+        json!({ "response": "ok" }).to_string()
+    } else {
+        body
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::core::game;
+    use std::time::Duration;
+
+    use crate::core::{
+        game,
+        mock_esl_server::{MockEslServer, MockResponse},
+        playback::DEFAULT_REPLAY_COPY_NAME,
+        test_support::TestDir,
+        transport::{HttpReportTransport, REPORT_TIMEOUT},
+    };
 
     use super::*;
 
-    #[test]
-    fn can_send_replay_to_server() {
-        let mut replay_info = ExtendedGameInformation {
+    fn test_game() -> ExtendedGameInformation {
+        ExtendedGameInformation {
             dev: Some(true),
             replay: Some("ABC".into()),
-            status: Default::default(),
             id: 1234,
-            name: "".into(),
             mod_chksum: 1234,
             mod_version: 1234,
-            md5: "".into(),
-            date: "".into(),
             ticks: 123,
             game: game::GameInfo {
-                name: "".into(),
-                mode: "".into(),
-                resources: "".into(),
-                locations: "".into(),
                 victory_points: 500,
+                ..Default::default()
             },
             map: Map {
                 name: "todo!()".to_string(),
@@ -261,15 +429,137 @@ mod tests {
                 width: 512,
                 height: 512,
             },
-            players: vec![],
-            messages: vec![],
-            actions: vec![],
-            aborted: false,
             frames: 123,
-            ended_at: "".into(),
-        };
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn can_send_replay_to_server() {
+        let server = MockEslServer::start(MockResponse::Accept);
+        let transport = HttpReportTransport::new(&server.endpoint(), REPORT_TIMEOUT).unwrap();
+
+        let mut replay_info = test_game();
+        let res = replay_info.send_replay_to_server(&transport, &PrivacySettings::default());
 
-        let res = replay_info.send_replay_to_server();
         assert!(res.is_ok());
+        assert_eq!(replay_info.status, json!({ "response": "ok" }).to_string());
+        assert!(replay_info.sent);
+        assert!(replay_info.replay.is_none());
+
+        let reports = server.received_reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0]["id"], "1234");
+        assert_eq!(reports[0]["replay"], "ABC");
+        assert_eq!(reports[0]["dev"], true);
+    }
+
+    #[test]
+    fn rejected_report_is_kept_in_status() {
+        let server = MockEslServer::start(MockResponse::Reject("Match already reported".into()));
+        let transport = HttpReportTransport::new(&server.endpoint(), REPORT_TIMEOUT).unwrap();
+
+        let mut replay_info = test_game();
+        replay_info
+            .send_replay_to_server(&transport, &PrivacySettings::default())
+            .unwrap();
+
+        assert!(replay_info.status.contains("Match already reported"));
+        assert_eq!(server.received_reports().len(), 1);
+    }
+
+    #[test]
+    fn server_error_is_reported_as_error_status() {
+        let server = MockEslServer::start(MockResponse::InternalServerError);
+        let transport = HttpReportTransport::new(&server.endpoint(), REPORT_TIMEOUT).unwrap();
+
+        let mut replay_info = test_game();
+        replay_info
+            .send_replay_to_server(&transport, &PrivacySettings::default())
+            .unwrap();
+
+        assert!(replay_info.status.contains("error"));
+        assert!(replay_info.status.contains("500"));
+        assert!(replay_info.replay.is_some());
+    }
+
+    #[test]
+    fn server_timeout_is_reported_as_error_status() {
+        let server = MockEslServer::start(MockResponse::Timeout(Duration::from_secs(2)));
+        let transport =
+            HttpReportTransport::new(&server.endpoint(), Duration::from_millis(200)).unwrap();
+
+        let mut replay_info = test_game();
+        replay_info
+            .send_replay_to_server(&transport, &PrivacySettings::default())
+            .unwrap();
+
+        assert!(replay_info.status.contains("error"));
+        assert!(replay_info.replay.is_some());
+    }
+
+    #[test]
+    fn dry_run_writes_report_and_replay_to_disk() {
+        let test_dir = TestDir::new("dry-run");
+        let output_dir = test_dir.join("dry-run");
+
+        let mut replay_info = test_game();
+        replay_info.map.path = "DATA:maps\\pvp\\6p_estia".into();
+        replay_info
+            .write_report_to_disk(
+                &output_dir,
+                &PathBuf::from("3v3.rec"),
+                DEFAULT_REPLAY_COPY_NAME,
+                &PrivacySettings::default(),
+            )
+            .unwrap();
+
+        let report_path = output_dir.join("1234_6p_estia.json");
+        assert!(report_path.exists());
+        assert!(output_dir.join("1234_6p_estia.rec").exists());
+        assert_eq!(
+            dry_run::find_report(&output_dir, 1234).unwrap(),
+            report_path
+        );
+
+        let payload: serde_json::Value =
+            serde_json::from_slice(&fs::read(&report_path).unwrap()).unwrap();
+        assert_eq!(payload["id"], "1234");
+        assert_eq!(payload["replay"], "ABC");
+
+        assert!(!replay_info.sent);
+        assert!(replay_info.status.contains("not sent"));
+    }
+
+    #[test]
+    fn warns_about_unexpected_mod_version() {
+        let mut replay_info = test_game();
+        replay_info.session.mod_version = "2.9.4".into();
+
+        replay_info.check_mod_version(Some("2.9.5"));
+        assert_eq!(replay_info.warnings.len(), 1);
+        assert!(replay_info.warnings[0].contains("2.9.4"));
+
+        let mut replay_info = test_game();
+        replay_info.session.mod_version = "2.9.5".into();
+        replay_info
+            .check_mod_version(Some("2.9.5"))
+            .check_mod_version(None);
+        assert!(replay_info.warnings.is_empty());
+    }
+
+    #[test]
+    fn warns_about_skipped_logfile_lines() {
+        let mut replay_info = test_game();
+        replay_info.note_skipped_lines(&[]);
+        assert!(replay_info.warnings.is_empty());
+
+        replay_info.note_skipped_lines(&[ParseDiagnostic {
+            line_no: 27,
+            line: "LoadArbitrator::UpdateLoadProgress - info".into(),
+            reason: "Steam id \"99999999999999999999999999\" is not a number".into(),
+        }]);
+        assert_eq!(replay_info.warnings.len(), 1);
+        assert!(replay_info.warnings[0].contains("line 27"));
     }
 }
@@ -0,0 +1,168 @@
+use serde::Serialize;
+
+use super::{
+    error::{ParserAppError, ParserAppResult},
+    history::{MatchRecord, PlayerRecord},
+    player_info::LogfilePlayerStatus,
+    stats::{find_player, WinRecord},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum Relation {
+    Teammates,
+    Opponents,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct HeadToHead {
+    pub player_id: u64,
+    pub opponent_id: u64,
+    /// Results from the point of view of `player_id`. Aborted games are not counted.
+    pub as_teammates: WinRecord,
+    pub as_opponents: WinRecord,
+    pub games: Vec<SharedGame>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SharedGame {
+    pub match_id: i64,
+    pub relic_match_id: usize,
+    pub map: String,
+    pub played_at: String,
+    pub aborted: bool,
+    pub relation: Relation,
+    pub player: PlayerRecord,
+    pub opponent: PlayerRecord,
+    pub player_won: bool,
+}
+
+/// Builds the shared history of two players, identified by either steam id or relic id
+pub fn head_to_head(
+    player_id: u64,
+    opponent_id: u64,
+    matches: &[MatchRecord],
+) -> ParserAppResult<HeadToHead> {
+    if player_id == opponent_id {
+        return Err(same_player_error(player_id, opponent_id));
+    }
+
+    let mut head_to_head = HeadToHead {
+        player_id,
+        opponent_id,
+        ..Default::default()
+    };
+
+    for record in matches.iter() {
+        let (Some(player), Some(opponent)) = (
+            find_player(record, player_id),
+            find_player(record, opponent_id),
+        ) else {
+            continue;
+        };
+
+        // The steam id and the relic id of the same player
+        if std::ptr::eq(player, opponent) {
+            return Err(same_player_error(player_id, opponent_id));
+        }
+
+        let relation = match player.team == opponent.team {
+            true => Relation::Teammates,
+            false => Relation::Opponents,
+        };
+        let player_won = player.status == LogfilePlayerStatus::Won;
+
+        if !record.aborted {
+            match relation {
+                Relation::Teammates => head_to_head.as_teammates.add(player_won),
+                Relation::Opponents => head_to_head.as_opponents.add(player_won),
+            }
+        }
+
+        head_to_head.games.push(SharedGame {
+            match_id: record.id,
+            relic_match_id: record.relic_match_id,
+            map: record.map.clone(),
+            played_at: record.played_at.clone(),
+            aborted: record.aborted,
+            relation,
+            player: player.clone(),
+            opponent: opponent.clone(),
+            player_won,
+        });
+    }
+
+    Ok(head_to_head)
+}
+
+fn same_player_error(player_id: u64, opponent_id: u64) -> ParserAppError {
+    ParserAppError::GenericError(format!(
+        "{player_id} and {opponent_id} are the same player, pick two different players"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{
+        game::ExtendedGameInformation, history::HistoryDatabase,
+        player_info::ExtendedPlayerInformation,
+    };
+
+    use super::*;
+
+    fn game(id: usize, teams: [u32; 3], winner: u32) -> ExtendedGameInformation {
+        let mut game = ExtendedGameInformation::new();
+        game.id = id;
+        game.map.path = format!("DATA:maps\\pvp\\map_{id}");
+        game.players = teams
+            .iter()
+            .enumerate()
+            .map(|(index, team)| ExtendedPlayerInformation {
+                steam_id: 76561198000000000 + index,
+                relic_id: 10000000 + index as u64,
+                team: *team,
+                status: match *team == winner {
+                    true => LogfilePlayerStatus::Won,
+                    false => LogfilePlayerStatus::Killed,
+                },
+                ..Default::default()
+            })
+            .collect();
+        game
+    }
+
+    #[test]
+    fn collects_shared_games() {
+        let history = HistoryDatabase::open_in_memory().unwrap();
+        history.save_game(&game(1, [0, 1, 1], 0)).unwrap();
+        history.save_game(&game(2, [0, 0, 1], 1)).unwrap();
+        history.save_game(&game(3, [0, 1, 0], 1)).unwrap();
+
+        // Player 0 by steam id, player 1 by relic id
+        let matches = history
+            .matches_with_players(76561198000000000, 10000001)
+            .unwrap();
+        let head_to_head = head_to_head(76561198000000000, 10000001, &matches).unwrap();
+
+        assert_eq!(head_to_head.games.len(), 3);
+        assert_eq!(head_to_head.games[0].relation, Relation::Opponents);
+        assert_eq!(head_to_head.games[1].relation, Relation::Teammates);
+        assert_eq!(head_to_head.games[0].map, "map_1");
+
+        assert_eq!(head_to_head.as_opponents.games, 2);
+        assert_eq!(head_to_head.as_opponents.wins, 1);
+        assert_eq!(head_to_head.as_teammates.games, 1);
+        assert_eq!(head_to_head.as_teammates.wins, 0);
+    }
+
+    #[test]
+    fn rejects_the_same_player_twice() {
+        let history = HistoryDatabase::open_in_memory().unwrap();
+        history.save_game(&game(1, [0, 1, 1], 0)).unwrap();
+
+        let matches = history
+            .matches_with_players(76561198000000000, 10000000)
+            .unwrap();
+        assert!(head_to_head(76561198000000000, 76561198000000000, &matches).is_err());
+        assert!(head_to_head(76561198000000000, 10000000, &matches).is_err());
+    }
+}
@@ -0,0 +1,1242 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    sync::{Mutex, MutexGuard},
+};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use super::{
+    annotations::normalize_tag,
+    error::{ParserAppError, ParserAppResult},
+    game::ExtendedGameInformation,
+    logfile::LeaverEvent,
+    player_info::LogfilePlayerStatus,
+};
+
+pub const HISTORY_DATABASE_FILE_NAME: &str = "history.sqlite3";
+const FRONTEND_STORE_FILE_NAME: &str = "store.json";
+const STORE_JSON_IMPORTED_KEY: &str = "store_json_imported";
+/// Local times as written by the replay and by the frontend into `store.json`
+const LOCAL_TIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%d.%m.%Y %H:%M",
+    "%Y/%m/%d %H:%M",
+];
+
+/// Schema migrations. Each entry is applied exactly once in order, tracked via `user_version`.
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE meta (
+        key TEXT PRIMARY KEY NOT NULL,
+        value TEXT NOT NULL
+    );
+
+    CREATE TABLE matches (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        relic_match_id INTEGER NOT NULL,
+        md5 TEXT NOT NULL,
+        name TEXT NOT NULL,
+        map TEXT NOT NULL,
+        played_at TEXT NOT NULL,
+        recorded_at TEXT NOT NULL,
+        ticks INTEGER NOT NULL,
+        frames INTEGER NOT NULL,
+        aborted INTEGER NOT NULL,
+        ranked INTEGER NOT NULL,
+        league INTEGER NOT NULL,
+        mod_version INTEGER NOT NULL,
+        local_steam_id INTEGER NOT NULL,
+        status TEXT NOT NULL,
+        sent INTEGER NOT NULL,
+        UNIQUE (relic_match_id, md5)
+    );
+
+    CREATE TABLE players (
+        match_id INTEGER NOT NULL REFERENCES matches (id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        slot INTEGER NOT NULL,
+        steam_id INTEGER NOT NULL,
+        relic_id INTEGER NOT NULL,
+        sim_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        team INTEGER NOT NULL,
+        race INTEGER NOT NULL,
+        hero INTEGER NOT NULL,
+        status TEXT NOT NULL,
+        PRIMARY KEY (match_id, position)
+    );
+
+    CREATE INDEX players_steam_id ON players (steam_id);
+    CREATE INDEX players_relic_id ON players (relic_id);
+
+    CREATE TABLE messages (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        match_id INTEGER NOT NULL REFERENCES matches (id) ON DELETE CASCADE,
+        tick INTEGER NOT NULL,
+        sender TEXT NOT NULL,
+        receiver TEXT NOT NULL,
+        body TEXT NOT NULL,
+        player_id INTEGER NOT NULL
+    );
+
+    CREATE INDEX messages_match_id ON messages (match_id);
+
+    CREATE TABLE report_attempts (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        match_id INTEGER NOT NULL REFERENCES matches (id) ON DELETE CASCADE,
+        attempted_at TEXT NOT NULL,
+        status TEXT NOT NULL,
+        sent INTEGER NOT NULL
+    );
+"#,
+    r#"
+    CREATE VIRTUAL TABLE messages_fts USING fts5 (
+        body,
+        content = 'messages',
+        content_rowid = 'id'
+    );
+
+    CREATE TRIGGER messages_fts_insert AFTER INSERT ON messages BEGIN
+        INSERT INTO messages_fts (rowid, body) VALUES (new.id, new.body);
+    END;
+
+    CREATE TRIGGER messages_fts_delete AFTER DELETE ON messages BEGIN
+        INSERT INTO messages_fts (messages_fts, rowid, body) VALUES ('delete', old.id, old.body);
+    END;
+
+    CREATE TRIGGER messages_fts_update AFTER UPDATE ON messages BEGIN
+        INSERT INTO messages_fts (messages_fts, rowid, body) VALUES ('delete', old.id, old.body);
+        INSERT INTO messages_fts (rowid, body) VALUES (new.id, new.body);
+    END;
+
+    INSERT INTO messages_fts (messages_fts) VALUES ('rebuild');
+"#,
+    r#"
+    ALTER TABLE matches ADD COLUMN starred INTEGER NOT NULL DEFAULT 0;
+"#,
+    r#"
+    ALTER TABLE matches ADD COLUMN notes TEXT NOT NULL DEFAULT '';
+
+    CREATE TABLE match_tags (
+        match_id INTEGER NOT NULL REFERENCES matches (id) ON DELETE CASCADE,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (match_id, tag)
+    );
+
+    CREATE INDEX match_tags_tag ON match_tags (tag);
+"#,
+    r#"
+    ALTER TABLE matches ADD COLUMN checksum_mismatch INTEGER NOT NULL DEFAULT 0;
+"#,
+    r#"
+    ALTER TABLE matches ADD COLUMN leaver_timeline TEXT NOT NULL DEFAULT '[]';
+"#,
+    r#"
+    ALTER TABLE matches ADD COLUMN played_at_utc TEXT NOT NULL DEFAULT '';
+
+    CREATE INDEX matches_played_at_utc ON matches (played_at_utc);
+"#,
+];
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MatchRecord {
+    pub id: i64,
+    pub relic_match_id: usize,
+    pub md5: String,
+    pub name: String,
+    pub map: String,
+    pub played_at: String,
+    /// `played_at` as a UTC timestamp that sorts chronologically, see `to_utc_timestamp`
+    pub played_at_utc: String,
+    pub recorded_at: String,
+    pub ticks: usize,
+    pub frames: usize,
+    pub aborted: bool,
+    pub ranked: bool,
+    pub league: bool,
+    pub checksum_mismatch: bool,
+    pub leaver_timeline: Vec<LeaverEvent>,
+    pub mod_version: usize,
+    pub local_steam_id: usize,
+    pub status: String,
+    pub sent: bool,
+    pub starred: bool,
+    pub notes: String,
+    pub tags: Vec<String>,
+    pub players: Vec<PlayerRecord>,
+    pub messages: Vec<MessageRecord>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PlayerRecord {
+    pub slot: usize,
+    pub steam_id: usize,
+    pub relic_id: u64,
+    pub sim_id: usize,
+    pub name: String,
+    pub team: u32,
+    pub race: usize,
+    pub hero: u32,
+    pub status: LogfilePlayerStatus,
+}
+
+/// A Steam profile that was logged in on this machine while games were recorded
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LocalProfile {
+    pub steam_id: usize,
+    /// In-game name of the most recent game of the profile
+    pub name: String,
+    pub matches: usize,
+    pub last_recorded_at: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MessageRecord {
+    pub tick: usize,
+    pub sender: String,
+    pub receiver: String,
+    pub body: String,
+    pub player_id: usize,
+}
+
+impl MatchRecord {
+    pub fn from(game: &ExtendedGameInformation) -> Self {
+        let recorded_at = now();
+        // Logfiles without a header carry no times, the replay at least knows the local minute
+        let played_at = match game.started_at.is_empty() {
+            true => game.date.clone(),
+            false => game.started_at.clone(),
+        };
+
+        Self {
+            id: 0,
+            relic_match_id: game.id,
+            md5: game.md5.clone(),
+            name: game.game.name.clone(),
+            map: game.map_name(),
+            played_at_utc: played_at_utc(&played_at, &recorded_at),
+            played_at,
+            recorded_at,
+            ticks: game.ticks,
+            frames: game.frames,
+            aborted: game.aborted,
+            ranked: game.ranked,
+            league: game.league,
+            checksum_mismatch: game.checksum_mismatch,
+            leaver_timeline: game.leaver_timeline.clone(),
+            mod_version: game.mod_version,
+            local_steam_id: game.local_steam_id,
+            status: game.status.clone(),
+            sent: game.sent,
+            starred: false,
+            notes: String::new(),
+            tags: vec![],
+            players: game
+                .players
+                .iter()
+                .map(|player| PlayerRecord {
+                    slot: player.slot,
+                    steam_id: player.steam_id,
+                    relic_id: player.relic_id,
+                    sim_id: player.sim_id,
+                    name: player.name.clone(),
+                    team: player.team,
+                    race: player.race,
+                    hero: player.hero,
+                    status: player.status.clone(),
+                })
+                .collect(),
+            messages: game
+                .messages
+                .iter()
+                .map(|message| MessageRecord {
+                    tick: message.tick as usize,
+                    sender: message.sender.clone(),
+                    receiver: message.receiver.clone(),
+                    body: message.body.clone(),
+                    player_id: message.player_id as usize,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Shape of a match as persisted by the frontend in `store.json` before the backend owned history
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct StoredMatchItem {
+    match_id: usize,
+    players: Vec<PlayerRecord>,
+    map: StoredMap,
+    duration: String,
+    status: Value,
+    played_at: String,
+    messages: Vec<MessageRecord>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct StoredMap {
+    path: String,
+}
+
+impl StoredMatchItem {
+    fn into_record(self) -> MatchRecord {
+        let sent = matches!(
+            self.status.get("response"),
+            Some(response) if response.as_str() != Some("not sent")
+        );
+
+        // The frontend did not keep track of when it stored a match, the game's end is close enough
+        let played_at_utc = played_at_utc(&self.played_at, &now());
+
+        MatchRecord {
+            relic_match_id: self.match_id,
+            map: self.map.path.replace("DATA:maps\\pvp\\", ""),
+            played_at: self.played_at,
+            recorded_at: played_at_utc.clone(),
+            played_at_utc,
+            ticks: duration_to_ticks(&self.duration),
+            status: self.status.to_string(),
+            sent,
+            players: self.players,
+            messages: self.messages,
+            ..Default::default()
+        }
+    }
+}
+
+/// Restricts queries to matches played within `[from, to)`. Both bounds are RFC 3339 dates or
+/// date times, e.g. `2022-11-16` or `2022-11-16T10:00:00Z`. Dates start at local midnight.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DateRange {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl DateRange {
+    /// Both bounds in the format of `played_at_utc`
+    fn utc_bounds(&self) -> ParserAppResult<(Option<String>, Option<String>)> {
+        let to_utc = |bound: &Option<String>| {
+            bound
+                .as_deref()
+                .map(|bound| {
+                    to_utc_timestamp(bound).ok_or_else(|| {
+                        ParserAppError::GenericError(format!("{bound:?} is not a valid date"))
+                    })
+                })
+                .transpose()
+        };
+
+        Ok((to_utc(&self.from)?, to_utc(&self.to)?))
+    }
+}
+
+pub struct HistoryDatabase {
+    connection: Mutex<Connection>,
+}
+
+impl HistoryDatabase {
+    pub fn open(database_path: &Path) -> ParserAppResult<Self> {
+        if let Some(parent) = database_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        Self::init(Connection::open(database_path)?)
+    }
+
+    pub fn open_in_memory() -> ParserAppResult<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(mut connection: Connection) -> ParserAppResult<Self> {
+        connection.pragma_update(None, "foreign_keys", true)?;
+        migrate(&mut connection)?;
+        backfill_played_at_utc(&mut connection)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    pub(crate) fn connection(&self) -> ParserAppResult<MutexGuard<'_, Connection>> {
+        self.connection.lock().map_err(|_| {
+            ParserAppError::GenericError("History database is unusable after a panic".into())
+        })
+    }
+
+    /// Stores a processed game together with the outcome of its report
+    pub fn save_game(&self, game: &ExtendedGameInformation) -> ParserAppResult<i64> {
+        let record = MatchRecord::from(game);
+
+        let mut connection = self.connection()?;
+        let tx = connection.transaction()?;
+        let id = upsert_match(&tx, &record)?;
+        tx.execute(
+            "INSERT INTO report_attempts (match_id, attempted_at, status, sent) VALUES (?1, ?2, ?3, ?4)",
+            params![id, record.recorded_at, record.status, record.sent],
+        )?;
+        tx.commit()?;
+
+        Ok(id)
+    }
+
+    /// Records a report that was sent outside of the pipeline, e.g. a dry run report submitted
+    /// later on. Returns the id of the stored match, or `None` if the match is not in the history.
+    pub fn record_report_attempt(
+        &self,
+        relic_match_id: usize,
+        status: &str,
+        sent: bool,
+    ) -> ParserAppResult<Option<i64>> {
+        let mut connection = self.connection()?;
+        let tx = connection.transaction()?;
+        let Some(id) = tx
+            .query_row(
+                "SELECT id FROM matches WHERE relic_match_id = ?1 ORDER BY id LIMIT 1",
+                [relic_match_id],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?
+        else {
+            return Ok(None);
+        };
+
+        tx.execute(
+            "UPDATE matches SET status = ?2, sent = sent OR ?3 WHERE id = ?1",
+            params![id, status, sent],
+        )?;
+        tx.execute(
+            "INSERT INTO report_attempts (match_id, attempted_at, status, sent) VALUES (?1, ?2, ?3, ?4)",
+            params![id, now(), status, sent],
+        )?;
+        tx.commit()?;
+
+        Ok(Some(id))
+    }
+
+    /// Stores a match that did not come out of the pipeline, e.g. from an imported history
+    pub fn save_record(&self, record: &MatchRecord) -> ParserAppResult<i64> {
+        let mut connection = self.connection()?;
+        let tx = connection.transaction()?;
+        let id = upsert_match(&tx, record)?;
+        tx.commit()?;
+
+        Ok(id)
+    }
+
+    /// Looks for a stored match that is the same game as `record`, either by relic match id or by
+    /// replay md5
+    pub fn find_duplicate(&self, record: &MatchRecord) -> ParserAppResult<Option<i64>> {
+        let connection = self.connection()?;
+        let id = connection
+            .query_row(
+                "SELECT id FROM matches
+                WHERE (?1 != 0 AND relic_match_id = ?1) OR (?2 != '' AND md5 = ?2)
+                LIMIT 1",
+                params![record.relic_match_id, record.md5],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(id)
+    }
+
+    pub fn list_matches(&self) -> ParserAppResult<Vec<MatchRecord>> {
+        let connection = self.connection()?;
+        let mut statement =
+            connection.prepare("SELECT * FROM matches ORDER BY played_at_utc DESC, id DESC")?;
+
+        let mut matches = statement
+            .query_map([], match_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut players = players_by_match(&connection)?;
+        let mut tags = tags_by_match(&connection)?;
+        for record in matches.iter_mut() {
+            record.players = players.remove(&record.id).unwrap_or_default();
+            record.tags = tags.remove(&record.id).unwrap_or_default();
+        }
+
+        Ok(matches)
+    }
+
+    /// The chat of every match by match id. `list_matches` leaves it out to stay small.
+    pub fn messages_by_match(&self) -> ParserAppResult<HashMap<i64, Vec<MessageRecord>>> {
+        let connection = self.connection()?;
+        let mut statement =
+            connection.prepare("SELECT * FROM messages ORDER BY match_id, tick, id")?;
+
+        let mut messages: HashMap<i64, Vec<MessageRecord>> = HashMap::new();
+        for row in statement.query_map([], |row| {
+            Ok((row.get::<_, i64>("match_id")?, message_from_row(row)?))
+        })? {
+            let (match_id, message) = row?;
+            messages.entry(match_id).or_default().push(message);
+        }
+
+        Ok(messages)
+    }
+
+    /// All games that were played to the end, in the order they were played
+    pub fn finished_matches(&self) -> ParserAppResult<Vec<MatchRecord>> {
+        let connection = self.connection()?;
+        let mut statement = connection
+            .prepare("SELECT * FROM matches WHERE aborted = 0 ORDER BY played_at_utc, id")?;
+
+        let mut matches = statement
+            .query_map([], match_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for record in matches.iter_mut() {
+            record.players = load_players(&connection, record.id)?;
+        }
+
+        Ok(matches)
+    }
+
+    /// Finished games the given player took part in, oldest first
+    pub fn matches_with_player(
+        &self,
+        steam_id: usize,
+        range: &DateRange,
+    ) -> ParserAppResult<Vec<MatchRecord>> {
+        let (from, to) = range.utc_bounds()?;
+
+        let connection = self.connection()?;
+        let mut statement = connection.prepare(
+            "SELECT * FROM matches
+            WHERE aborted = 0
+                AND EXISTS (SELECT 1 FROM players WHERE players.match_id = matches.id AND players.steam_id = ?1)
+                AND (?2 IS NULL OR played_at_utc >= ?2)
+                AND (?3 IS NULL OR played_at_utc < ?3)
+            ORDER BY played_at_utc, id",
+        )?;
+
+        let mut matches = statement
+            .query_map(params![steam_id, from, to], match_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for record in matches.iter_mut() {
+            record.players = load_players(&connection, record.id)?;
+        }
+
+        Ok(matches)
+    }
+
+    /// Games both players took part in, oldest first. Players are identified by either their
+    /// steam id or their relic id.
+    pub fn matches_with_players(
+        &self,
+        first_player_id: u64,
+        second_player_id: u64,
+    ) -> ParserAppResult<Vec<MatchRecord>> {
+        let connection = self.connection()?;
+        let mut statement = connection.prepare(
+            "SELECT * FROM matches
+            WHERE EXISTS (
+                    SELECT 1 FROM players WHERE players.match_id = matches.id
+                        AND (players.steam_id = ?1 OR players.relic_id = ?1)
+                )
+                AND EXISTS (
+                    SELECT 1 FROM players WHERE players.match_id = matches.id
+                        AND (players.steam_id = ?2 OR players.relic_id = ?2)
+                )
+            ORDER BY played_at_utc, id",
+        )?;
+
+        let mut matches = statement
+            .query_map(params![first_player_id, second_player_id], match_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for record in matches.iter_mut() {
+            record.players = load_players(&connection, record.id)?;
+        }
+
+        Ok(matches)
+    }
+
+    /// Steam profiles that played on this machine, most recently active first
+    pub fn local_profiles(&self) -> ParserAppResult<Vec<LocalProfile>> {
+        let connection = self.connection()?;
+        let mut statement = connection.prepare(
+            "SELECT
+                local_steam_id,
+                COUNT(*) AS matches,
+                MAX(recorded_at) AS last_recorded_at,
+                (
+                    SELECT players.name FROM players
+                    JOIN matches AS played ON played.id = players.match_id
+                    WHERE players.steam_id = matches.local_steam_id
+                    ORDER BY played.recorded_at DESC, played.id DESC LIMIT 1
+                ) AS name
+            FROM matches
+            WHERE local_steam_id != 0
+            GROUP BY local_steam_id
+            ORDER BY last_recorded_at DESC",
+        )?;
+
+        let profiles = statement
+            .query_map([], |row| {
+                Ok(LocalProfile {
+                    steam_id: row.get("local_steam_id")?,
+                    name: row.get::<_, Option<String>>("name")?.unwrap_or_default(),
+                    matches: row.get("matches")?,
+                    last_recorded_at: row.get("last_recorded_at")?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(profiles)
+    }
+
+    /// Steam profile that was logged in during the most recently played game. Games processed on
+    /// this machine take precedence over imported ones, whose local profile is somebody else's.
+    pub fn latest_local_steam_id(&self) -> ParserAppResult<Option<usize>> {
+        let connection = self.connection()?;
+        let steam_id = connection
+            .query_row(
+                "SELECT local_steam_id FROM matches WHERE local_steam_id != 0
+                ORDER BY
+                    EXISTS (SELECT 1 FROM report_attempts WHERE report_attempts.match_id = matches.id) DESC,
+                    played_at_utc DESC,
+                    id DESC
+                LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(steam_id)
+    }
+
+    pub fn match_by_relic_id(&self, relic_match_id: usize) -> ParserAppResult<Option<MatchRecord>> {
+        let connection = self.connection()?;
+        let Some(mut record) = connection
+            .query_row(
+                "SELECT * FROM matches WHERE relic_match_id = ?1 ORDER BY id LIMIT 1",
+                [relic_match_id],
+                match_from_row,
+            )
+            .optional()?
+        else {
+            return Ok(None);
+        };
+
+        record.players = load_players(&connection, record.id)?;
+
+        Ok(Some(record))
+    }
+
+    pub fn delete_match(&self, id: i64) -> ParserAppResult<()> {
+        self.connection()?
+            .execute("DELETE FROM matches WHERE id = ?1", [id])?;
+
+        Ok(())
+    }
+
+    pub fn load_match(&self, id: i64) -> ParserAppResult<Option<MatchRecord>> {
+        let connection = self.connection()?;
+        let Some(mut record) = connection
+            .query_row("SELECT * FROM matches WHERE id = ?1", [id], match_from_row)
+            .optional()?
+        else {
+            return Ok(None);
+        };
+
+        record.players = load_players(&connection, id)?;
+        record.messages = load_messages(&connection, id)?;
+        record.tags = load_tags(&connection, id)?;
+
+        Ok(Some(record))
+    }
+
+    /// One-time import of the match history the frontend kept in `store.json`. Returns the
+    /// number of imported matches.
+    pub fn import_store_json(&self, store_path: &Path) -> ParserAppResult<usize> {
+        let mut connection = self.connection()?;
+
+        let already_imported = connection
+            .query_row(
+                "SELECT value FROM meta WHERE key = ?1",
+                [STORE_JSON_IMPORTED_KEY],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?
+            .is_some();
+
+        if already_imported || !store_path.exists() {
+            return Ok(0);
+        }
+
+        let store: Value = serde_json::from_str(&fs::read_to_string(store_path)?)?;
+        let mut items: Vec<StoredMatchItem> = match store.get("matches") {
+            Some(matches) => serde_json::from_value(matches.clone())?,
+            None => vec![],
+        };
+        // The frontend lists the newest match first, insert them in the order they were played
+        items.reverse();
+        items.sort_by_cached_key(|item| to_utc_timestamp(&item.played_at));
+
+        let tx = connection.transaction()?;
+        let count = items.len();
+        for item in items {
+            upsert_match(&tx, &item.into_record())?;
+        }
+        tx.execute(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2)",
+            params![STORE_JSON_IMPORTED_KEY, now()],
+        )?;
+        tx.commit()?;
+
+        tracing::info!("Imported {count} matches from {store_path:?}");
+        Ok(count)
+    }
+}
+
+/// Opens the history database in the app data dir, imports the frontend history on first run and
+/// hands the database to Tauri's state management
+pub fn setup_history_database(handle: &AppHandle) -> ParserAppResult<()> {
+    let app_data_dir = handle.path().app_data_dir()?;
+    let history = HistoryDatabase::open(&app_data_dir.join(HISTORY_DATABASE_FILE_NAME))?;
+
+    history.import_store_json(&app_data_dir.join(FRONTEND_STORE_FILE_NAME))?;
+    handle.manage(history);
+
+    Ok(())
+}
+
+fn migrate(connection: &mut Connection) -> ParserAppResult<()> {
+    let version: usize = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        tracing::info!("Migrating history database to version {}", index + 1);
+
+        let tx = connection.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+/// Fills `played_at_utc` of the matches that were stored before the column existed
+fn backfill_played_at_utc(connection: &mut Connection) -> ParserAppResult<()> {
+    let tx = connection.transaction()?;
+    let matches = tx
+        .prepare("SELECT id, played_at, recorded_at FROM matches WHERE played_at_utc = ''")?
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (id, played_at, recorded_at) in matches {
+        tx.execute(
+            "UPDATE matches SET played_at_utc = ?2 WHERE id = ?1",
+            params![id, played_at_utc(&played_at, &recorded_at)],
+        )?;
+    }
+    tx.commit()?;
+
+    Ok(())
+}
+
+pub(crate) fn upsert_match(tx: &Transaction, record: &MatchRecord) -> ParserAppResult<i64> {
+    tx.execute(
+        "INSERT INTO matches (
+            relic_match_id, md5, name, map, played_at, recorded_at, ticks, frames, aborted,
+            ranked, league, mod_version, local_steam_id, status, sent, starred, notes,
+            checksum_mismatch, leaver_timeline, played_at_utc
+        ) VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19,
+            ?20
+        )
+        ON CONFLICT (relic_match_id, md5) DO UPDATE SET
+            status = excluded.status,
+            sent = excluded.sent OR matches.sent",
+        params![
+            record.relic_match_id,
+            record.md5,
+            record.name,
+            record.map,
+            record.played_at,
+            record.recorded_at,
+            record.ticks,
+            record.frames,
+            record.aborted,
+            record.ranked,
+            record.league,
+            record.mod_version,
+            record.local_steam_id,
+            record.status,
+            record.sent,
+            record.starred,
+            record.notes,
+            record.checksum_mismatch,
+            serde_json::to_string(&record.leaver_timeline)?,
+            played_at_utc(&record.played_at, &record.recorded_at),
+        ],
+    )?;
+
+    let id: i64 = tx.query_row(
+        "SELECT id FROM matches WHERE relic_match_id = ?1 AND md5 = ?2",
+        params![record.relic_match_id, record.md5],
+        |row| row.get(0),
+    )?;
+
+    // Players and messages of a game never change - replace them wholesale
+    tx.execute("DELETE FROM players WHERE match_id = ?1", [id])?;
+    tx.execute("DELETE FROM messages WHERE match_id = ?1", [id])?;
+
+    for (position, player) in record.players.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO players (
+                match_id, position, slot, steam_id, relic_id, sim_id, name, team, race, hero, status
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                id,
+                position,
+                player.slot,
+                player.steam_id,
+                player.relic_id,
+                player.sim_id,
+                player.name,
+                player.team,
+                player.race,
+                player.hero,
+                status_to_str(&player.status),
+            ],
+        )?;
+    }
+
+    for message in record.messages.iter() {
+        tx.execute(
+            "INSERT INTO messages (match_id, tick, sender, receiver, body, player_id)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                id,
+                message.tick,
+                message.sender,
+                message.receiver,
+                message.body,
+                message.player_id,
+            ],
+        )?;
+    }
+
+    // Tags are annotations of the user, saving a game again must not drop them
+    for tag in record.tags.iter().filter_map(|tag| normalize_tag(tag)) {
+        tx.execute(
+            "INSERT OR IGNORE INTO match_tags (match_id, tag) VALUES (?1, ?2)",
+            params![id, tag],
+        )?;
+    }
+
+    Ok(id)
+}
+
+pub(crate) fn match_from_row(row: &Row) -> rusqlite::Result<MatchRecord> {
+    Ok(MatchRecord {
+        id: row.get("id")?,
+        relic_match_id: row.get("relic_match_id")?,
+        md5: row.get("md5")?,
+        name: row.get("name")?,
+        map: row.get("map")?,
+        played_at: row.get("played_at")?,
+        played_at_utc: row.get("played_at_utc")?,
+        recorded_at: row.get("recorded_at")?,
+        ticks: row.get("ticks")?,
+        frames: row.get("frames")?,
+        aborted: row.get("aborted")?,
+        ranked: row.get("ranked")?,
+        league: row.get("league")?,
+        checksum_mismatch: row.get("checksum_mismatch")?,
+        // A broken timeline must not hide the match
+        leaver_timeline: serde_json::from_str(&row.get::<_, String>("leaver_timeline")?)
+            .unwrap_or_default(),
+        mod_version: row.get("mod_version")?,
+        local_steam_id: row.get("local_steam_id")?,
+        status: row.get("status")?,
+        sent: row.get("sent")?,
+        starred: row.get("starred")?,
+        notes: row.get("notes")?,
+        tags: vec![],
+        players: vec![],
+        messages: vec![],
+    })
+}
+
+pub(crate) fn load_players(
+    connection: &Connection,
+    match_id: i64,
+) -> ParserAppResult<Vec<PlayerRecord>> {
+    let mut statement =
+        connection.prepare("SELECT * FROM players WHERE match_id = ?1 ORDER BY position")?;
+
+    let players = statement
+        .query_map([match_id], player_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(players)
+}
+
+/// The players of all matches at once, by match id
+fn players_by_match(connection: &Connection) -> ParserAppResult<HashMap<i64, Vec<PlayerRecord>>> {
+    let mut statement = connection.prepare(
+        "SELECT players.* FROM players
+        JOIN matches ON matches.id = players.match_id
+        ORDER BY players.match_id, players.position",
+    )?;
+
+    let mut players: HashMap<i64, Vec<PlayerRecord>> = HashMap::new();
+    for row in statement.query_map([], |row| {
+        Ok((row.get::<_, i64>("match_id")?, player_from_row(row)?))
+    })? {
+        let (match_id, player) = row?;
+        players.entry(match_id).or_default().push(player);
+    }
+
+    Ok(players)
+}
+
+fn player_from_row(row: &Row) -> rusqlite::Result<PlayerRecord> {
+    Ok(PlayerRecord {
+        slot: row.get("slot")?,
+        steam_id: row.get("steam_id")?,
+        relic_id: row.get("relic_id")?,
+        sim_id: row.get("sim_id")?,
+        name: row.get("name")?,
+        team: row.get("team")?,
+        race: row.get("race")?,
+        hero: row.get("hero")?,
+        status: status_from_str(&row.get::<_, String>("status")?),
+    })
+}
+
+pub(crate) fn load_tags(connection: &Connection, match_id: i64) -> ParserAppResult<Vec<String>> {
+    let mut statement =
+        connection.prepare("SELECT tag FROM match_tags WHERE match_id = ?1 ORDER BY tag")?;
+
+    let tags = statement
+        .query_map([match_id], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(tags)
+}
+
+/// The tags of all matches at once, by match id
+fn tags_by_match(connection: &Connection) -> ParserAppResult<HashMap<i64, Vec<String>>> {
+    let mut statement =
+        connection.prepare("SELECT match_id, tag FROM match_tags ORDER BY match_id, tag")?;
+
+    let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+    for row in statement.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+    })? {
+        let (match_id, tag) = row?;
+        tags.entry(match_id).or_default().push(tag);
+    }
+
+    Ok(tags)
+}
+
+fn load_messages(connection: &Connection, match_id: i64) -> ParserAppResult<Vec<MessageRecord>> {
+    let mut statement =
+        connection.prepare("SELECT * FROM messages WHERE match_id = ?1 ORDER BY tick, id")?;
+
+    let messages = statement
+        .query_map([match_id], message_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(messages)
+}
+
+fn message_from_row(row: &Row) -> rusqlite::Result<MessageRecord> {
+    Ok(MessageRecord {
+        tick: row.get("tick")?,
+        sender: row.get("sender")?,
+        receiver: row.get("receiver")?,
+        body: row.get("body")?,
+        player_id: row.get("player_id")?,
+    })
+}
+
+pub(crate) fn status_to_str(status: &LogfilePlayerStatus) -> String {
+    format!("{status:?}")
+}
+
+pub(crate) fn status_from_str(status: &str) -> LogfilePlayerStatus {
+    serde_json::from_value(Value::String(status.into())).unwrap_or_default()
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// `played_at` as a sortable timestamp, or `recorded_at` if `played_at` is no known date
+fn played_at_utc(played_at: &str, recorded_at: &str) -> String {
+    to_utc_timestamp(played_at)
+        .or_else(|| to_utc_timestamp(recorded_at))
+        .unwrap_or_default()
+}
+
+/// Normalizes RFC 3339 date times, plain dates and the local times of replays to
+/// `YYYY-MM-DDTHH:MM:SSZ`, which sorts chronologically. Times without an offset are taken to be
+/// local times of this machine.
+pub(crate) fn to_utc_timestamp(value: &str) -> Option<String> {
+    let value = value.trim();
+
+    let time = match DateTime::parse_from_rfc3339(value) {
+        Ok(time) => time.with_timezone(&Utc),
+        Err(_) => {
+            let local_time = LOCAL_TIME_FORMATS
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+                .or_else(|| {
+                    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                        .ok()
+                        .and_then(|date| date.and_hms_opt(0, 0, 0))
+                })?;
+
+            Local
+                .from_local_datetime(&local_time)
+                .earliest()?
+                .with_timezone(&Utc)
+        }
+    };
+
+    Some(time.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// Reverses the frontend's `ticks2time`, i.e. "mm:ss" at 10 ticks per second
+fn duration_to_ticks(duration: &str) -> usize {
+    let Some((minutes, seconds)) = duration.split_once(':') else {
+        return 0;
+    };
+
+    let minutes = minutes.parse::<usize>().unwrap_or_default();
+    let seconds = seconds.parse::<usize>().unwrap_or_default();
+
+    (minutes * 60 + seconds) * 10
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{
+        logfile::LeaveKind,
+        test_support::{game, TestDir},
+    };
+
+    use super::*;
+
+    #[test]
+    fn saves_and_loads_games() {
+        let history = HistoryDatabase::open_in_memory().unwrap();
+        let id = history.save_game(&game(1)).unwrap();
+        history.save_game(&game(2)).unwrap();
+
+        // Saving the same game again only records another report attempt
+        assert_eq!(history.save_game(&game(1)).unwrap(), id);
+        history.set_tags(id, &["scrim".into()]).unwrap();
+
+        let matches = history.list_matches().unwrap();
+        assert_eq!(matches.len(), 2);
+        let listed = matches.iter().find(|record| record.id == id).unwrap();
+        assert_eq!(listed.players.len(), 2);
+        assert_eq!(listed.tags, vec!["scrim".to_string()]);
+
+        let record = history.load_match(id).unwrap().unwrap();
+        assert_eq!(record.map, "6p_estia");
+        assert_eq!(record.players.len(), 2);
+        assert_eq!(record.players[0].status, LogfilePlayerStatus::Won);
+        assert_eq!(record.players[1].team, 1);
+
+        let attempts: usize = history
+            .connection()
+            .unwrap()
+            .query_row(
+                "SELECT COUNT(*) FROM report_attempts WHERE match_id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(attempts, 2);
+
+        let mut left_early = game(3);
+        left_early.leaver_timeline = vec![LeaverEvent {
+            kind: LeaveKind::Dropped,
+            at: "2022-11-16T17:21:56+01:00".into(),
+            frame: Some(8390),
+            game_time_secs: Some(839),
+            station: Some(2),
+            sim_id: Some(1000),
+            reason_code: None,
+        }];
+        let left_early_id = history.save_game(&left_early).unwrap();
+        let record = history.load_match(left_early_id).unwrap().unwrap();
+        assert_eq!(record.leaver_timeline, left_early.leaver_timeline);
+    }
+
+    #[test]
+    fn filters_and_orders_by_played_at() {
+        let history = HistoryDatabase::open_in_memory().unwrap();
+
+        // The logfile time wins over the local minute of the replay
+        let mut evening = game(1);
+        evening.date = "2022-11-16 08:00".into();
+        evening.started_at = "2022-11-16T19:00:00+01:00".into();
+        evening.local_steam_id = 1;
+        let evening_id = history.save_game(&evening).unwrap();
+        let record = history.load_match(evening_id).unwrap().unwrap();
+        assert_eq!(record.played_at, "2022-11-16T19:00:00+01:00");
+        assert_eq!(record.played_at_utc, "2022-11-16T18:00:00Z");
+
+        let mut morning = game(2);
+        morning.date = "2022-11-16T09:00:00Z".into();
+        history.save_game(&morning).unwrap();
+
+        let played = history
+            .matches_with_player(1, &DateRange::default())
+            .unwrap()
+            .iter()
+            .map(|record| record.relic_match_id)
+            .collect::<Vec<_>>();
+        assert_eq!(played, vec![2, 1]);
+
+        let afternoon = DateRange {
+            from: Some("2022-11-16T12:00:00Z".into()),
+            to: None,
+        };
+        let played = history.matches_with_player(1, &afternoon).unwrap();
+        assert_eq!(played.len(), 1);
+        assert_eq!(played[0].relic_match_id, 1);
+
+        // A teammate's imported game is more recent, but was not played on this machine
+        history
+            .save_record(&MatchRecord {
+                relic_match_id: 3,
+                played_at: "2022-11-17T18:00:00Z".into(),
+                local_steam_id: 2,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(history.latest_local_steam_id().unwrap(), Some(1));
+    }
+
+    #[test]
+    fn records_later_report_attempts() {
+        let history = HistoryDatabase::open_in_memory().unwrap();
+        let id = history.save_game(&game(1)).unwrap();
+
+        let status = r#"{"response":"ok"}"#;
+        assert_eq!(
+            history.record_report_attempt(1, status, true).unwrap(),
+            Some(id)
+        );
+        assert_eq!(
+            history.record_report_attempt(2, status, true).unwrap(),
+            None
+        );
+
+        let record = history.load_match(id).unwrap().unwrap();
+        assert!(record.sent);
+        assert_eq!(record.status, status);
+    }
+
+    #[test]
+    fn lists_every_local_profile() {
+        let history = HistoryDatabase::open_in_memory().unwrap();
+
+        let mut alice_game = game(1);
+        alice_game.local_steam_id = 1;
+        history.save_game(&alice_game).unwrap();
+
+        let mut bob_game = game(2);
+        bob_game.local_steam_id = 2;
+        history.save_game(&bob_game).unwrap();
+        history.save_game(&game(3)).unwrap();
+
+        let mut profiles = history.local_profiles().unwrap();
+        profiles.sort_by_key(|profile| profile.steam_id);
+        let profiles = profiles
+            .iter()
+            .map(|profile| (profile.steam_id, profile.name.as_str(), profile.matches))
+            .collect::<Vec<_>>();
+        assert_eq!(profiles, vec![(1, "Alice", 1), (2, "Bob", 1)]);
+    }
+
+    #[test]
+    fn normalizes_played_at() {
+        assert_eq!(
+            to_utc_timestamp("2022-11-16T17:21:56+01:00").as_deref(),
+            Some("2022-11-16T16:21:56Z")
+        );
+        assert!(to_utc_timestamp("2022-11-16 11:39").is_some());
+        assert!(to_utc_timestamp("2022-11-16").is_some());
+        assert_eq!(to_utc_timestamp("yesterday"), None);
+        assert_eq!(
+            played_at_utc("yesterday", "2022-11-16T10:00:00Z"),
+            "2022-11-16T10:00:00Z"
+        );
+    }
+
+    #[test]
+    fn imports_store_json_once() {
+        let test_dir = TestDir::new("store-import");
+        let store_path = test_dir.join("store.json");
+        fs::write(
+            &store_path,
+            r#"{
+                "matches": [{
+                    "match_id": 54864600,
+                    "players": [{ "name": "Alice", "steam_id": 1, "team": 0, "status": "Won" }],
+                    "map": { "path": "DATA:maps\\pvp\\2p_calderisdunes" },
+                    "duration": "11:00",
+                    "status": { "response": "ok" },
+                    "played_at": "2022-11-16 11:39",
+                    "messages": [{ "tick": 10, "sender": "Alice", "receiver": "All", "body": "gg", "player_id": 1000 }]
+                }, {
+                    "match_id": 54864500,
+                    "map": { "path": "DATA:maps\\pvp\\6p_estia" },
+                    "status": { "response": "not sent" },
+                    "played_at": "2022-11-15 20:05"
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let history = HistoryDatabase::open_in_memory().unwrap();
+        assert_eq!(history.import_store_json(&store_path).unwrap(), 2);
+        assert_eq!(history.import_store_json(&store_path).unwrap(), 0);
+
+        // Newest first, but the older match was inserted first
+        let matches = history.list_matches().unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].relic_match_id, 54864600);
+        assert!(matches[0].id > matches[1].id);
+        assert!(matches[0].played_at_utc > matches[1].played_at_utc);
+        assert!(!matches[1].sent);
+        assert_eq!(matches[0].map, "2p_calderisdunes");
+        assert_eq!(matches[0].ticks, 6600);
+        assert!(matches[0].sent);
+
+        let record = history.load_match(matches[0].id).unwrap().unwrap();
+        assert_eq!(record.messages[0].body, "gg");
+    }
+}
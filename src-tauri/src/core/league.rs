@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::game::ExtendedGameInformation;
+
+/// Rules deciding whether a game is reported as a league game. A game counts as a league game
+/// as soon as one of the configured rules matches.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LeagueRules {
+    /// Regular expressions matched against the lobby name, e.g. `^\[ESL\]`
+    pub game_name_patterns: Vec<String>,
+    /// Match codes handed out by the league admins that players put into the lobby name
+    pub match_codes: Vec<String>,
+    /// Registered league teams. Every team of a game has to be made up of a single roster. Games
+    /// whose teams fit several rosters are not counted, the roster can not be told then.
+    pub rosters: Vec<LeagueRoster>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LeagueRoster {
+    pub name: String,
+    pub steam_ids: Vec<usize>,
+}
+
+impl LeagueRules {
+    pub fn is_league_game(&self, game: &ExtendedGameInformation) -> bool {
+        self.matches_game_name(&game.game.name)
+            || self.matches_match_code(&game.game.name)
+            || self.matches_rosters(game)
+    }
+
+    fn matches_game_name(&self, game_name: &str) -> bool {
+        self.game_name_patterns
+            .iter()
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(regex) => Some(regex),
+                Err(e) => {
+                    tracing::warn!("Ignoring invalid league game name pattern {pattern:?}: {e}");
+                    None
+                }
+            })
+            .any(|regex| regex.is_match(game_name))
+    }
+
+    fn matches_match_code(&self, game_name: &str) -> bool {
+        let game_name = game_name.to_lowercase();
+
+        self.match_codes
+            .iter()
+            .filter(|code| !code.trim().is_empty())
+            .any(|code| game_name.contains(&code.trim().to_lowercase()))
+    }
+
+    fn matches_rosters(&self, game: &ExtendedGameInformation) -> bool {
+        let mut teams: HashMap<u32, Vec<usize>> = HashMap::new();
+        for player in game.players.iter() {
+            teams.entry(player.team).or_default().push(player.steam_id);
+        }
+
+        if teams.len() < 2 {
+            return false;
+        }
+
+        let mut team_rosters = vec![];
+        for steam_ids in teams.values() {
+            let mut rosters = self
+                .rosters
+                .iter()
+                .enumerate()
+                .filter(|(_, roster)| {
+                    steam_ids
+                        .iter()
+                        .all(|steam_id| roster.steam_ids.contains(steam_id))
+                })
+                .map(|(index, roster)| (index, &roster.name));
+
+            match (rosters.next(), rosters.next()) {
+                (Some((index, _)), None) => team_rosters.push(index),
+                (Some((_, first)), Some((_, second))) => {
+                    tracing::warn!(
+                        "Players {steam_ids:?} are on the rosters of both {first:?} and {second:?}, not counting the game as a league game"
+                    );
+                    return false;
+                }
+                (None, _) => return false,
+            }
+        }
+
+        // Two teams made up of the same roster is an internal scrim, not a league game
+        let team_count = team_rosters.len();
+        team_rosters.sort_unstable();
+        team_rosters.dedup();
+
+        team_rosters.len() == team_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::player_info::ExtendedPlayerInformation;
+
+    use super::*;
+
+    fn player(steam_id: usize, team: u32) -> ExtendedPlayerInformation {
+        ExtendedPlayerInformation {
+            steam_id,
+            team,
+            ..Default::default()
+        }
+    }
+
+    fn game(name: &str, players: Vec<ExtendedPlayerInformation>) -> ExtendedGameInformation {
+        let mut game = ExtendedGameInformation::new();
+        game.game.name = name.into();
+        game.players = players;
+        game
+    }
+
+    #[test]
+    fn detects_league_game_by_name_pattern() {
+        let rules = LeagueRules {
+            game_name_patterns: vec![r"^\[ESL\]".into()],
+            ..Default::default()
+        };
+
+        assert!(rules.is_league_game(&game("[ESL] Week 3", vec![])));
+        assert!(!rules.is_league_game(&game("Casual 3v3", vec![])));
+    }
+
+    #[test]
+    fn detects_league_game_by_match_code() {
+        let rules = LeagueRules {
+            match_codes: vec!["XK42".into()],
+            ..Default::default()
+        };
+
+        assert!(rules.is_league_game(&game("finals xk42", vec![])));
+        assert!(!rules.is_league_game(&game("finals", vec![])));
+    }
+
+    #[test]
+    fn detects_league_game_by_rosters() {
+        let rules = LeagueRules {
+            rosters: vec![
+                LeagueRoster {
+                    name: "Blue".into(),
+                    steam_ids: vec![1, 2, 3],
+                },
+                LeagueRoster {
+                    name: "Red".into(),
+                    steam_ids: vec![4, 5, 6],
+                },
+            ],
+            ..Default::default()
+        };
+
+        let league_game = game(
+            "",
+            vec![player(1, 0), player(2, 0), player(4, 1), player(6, 1)],
+        );
+        let mixed_game = game(
+            "",
+            vec![player(1, 0), player(4, 0), player(2, 1), player(5, 1)],
+        );
+        let scrim = game("", vec![player(1, 0), player(2, 1)]);
+
+        assert!(rules.is_league_game(&league_game));
+        assert!(!rules.is_league_game(&mixed_game));
+        assert!(!rules.is_league_game(&scrim));
+    }
+
+    #[test]
+    fn rejects_teams_that_fit_several_rosters() {
+        let rules = LeagueRules {
+            rosters: vec![
+                LeagueRoster {
+                    name: "Blue".into(),
+                    steam_ids: vec![1, 2, 3],
+                },
+                LeagueRoster {
+                    name: "Blue Academy".into(),
+                    steam_ids: vec![1, 2, 7],
+                },
+                LeagueRoster {
+                    name: "Red".into(),
+                    steam_ids: vec![4, 5, 6],
+                },
+            ],
+            ..Default::default()
+        };
+
+        let ambiguous_game = game(
+            "",
+            vec![player(1, 0), player(2, 0), player(4, 1), player(5, 1)],
+        );
+        let academy_game = game(
+            "",
+            vec![player(1, 0), player(7, 0), player(4, 1), player(5, 1)],
+        );
+
+        assert!(!rules.is_league_game(&ambiguous_game));
+        assert!(rules.is_league_game(&academy_game));
+    }
+}
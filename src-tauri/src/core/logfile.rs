@@ -1,27 +1,43 @@
 use std::{collections::HashMap, fs::File, io::Read, path::Path};
 
+use chrono::{
+    DateTime, Duration, FixedOffset, NaiveDateTime, NaiveTime, SecondsFormat, TimeZone, Utc,
+};
 use encoding_rs_io::DecodeReaderBytesBuilder;
 use regex::{Captures, Regex, RegexSet};
 use serde::{Deserialize, Serialize};
 
 use super::{
     error::{ParserAppError, ParserAppResult},
-    player_info::LogfilePlayerInfo,
+    player_info::{LogfilePlayerInfo, LogfilePlayerStatus},
 };
 
-const MATCH_BLOCK_PATTERNS: [&str; 10] = [
-    r"Match Started - \[\d+:(.+) /steam/(\d+)\], slot =\D+(\d)",
+const MATCH_BLOCK_PATTERNS: [&str; 17] = [
+    r"Match Started - \[\d+:(.+) /steam/(\d+)\], slot =\D+(\d)(?:, ranking =\s*(-?\d+))?",
     r"Beginning mission (.+) \((\d) Humans, (\d) Computers\)",
-    r"GAME -- Frame",
+    r"GAME -- Frame(?: (\d+) - SchedulePeerForDestruction - peer (\d+))?",
     r"SimID:(\d+), raceID:(\d+), teamID:(\d+), uid:\d+:(\d+), result:\d{1}:(.+)",
     r"SimID:(\d+), raceID:(\d+), teamID:(\d+), uid:\[\d+:(.+)\]",
     r"ReportSimStats - storing simulation results for match \d:(\d+)",
     r"Ending mission - '(\D+)'",
     r"Game Over at frame (\d+)",
-    r"pid 0:(\d+), /steam/(\d+)",
+    r#"pid 0:(\d+), /steam/(\d+)\]"(?: (has started|finished) loading with checksum \[(-?\d+)\])?"#,
     r"Found profile: /steam/(\d+)",
+    r"^[\d:.]+\s+(.+DOW2\.exe) started at (\d{4}-\d{2}-\d{2} \d{2}:\d{2})",
+    r"OS (.+?), (\d+) MB Physical Memory",
+    r"RUN OPTIONS: (.*)",
+    r"Initializing mod '(.+?)', version '(.+?)'",
+    r"XTHREAD: Detected (\d+) core\(s\) with (\d+) hardware thread\(s\)",
+    r"SchedulePeerForDestruction - info, frame \[\d+\], peer for station \[(\d+)\]",
+    r"remote station \[(\d+)\] was disconnected, disconnect reason code given \[(\d+)\]",
 ];
 
+/// Time zones are whole quarter hours, the log clock is rounded to them
+const TIMEZONE_GRANULARITY_SECS: i64 = 15 * 60;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+/// The simulation runs at a fixed rate, which turns frames into game time
+const SIM_FRAMES_PER_SECOND: usize = 10;
+
 lazy_static! {
     static ref LOGFILE_FILTER_REGEXP: RegexSet = regex::RegexSet::new([
         r"Beginning mission",
@@ -33,10 +49,26 @@ lazy_static! {
         r"Match Started",
         r"MOD -- Game Over at frame",
         r"LoadArbitrator::UpdateLoadProgress - info",
+        r"LoadArbitrator::UpdateLoadProgress - player .+ has started loading",
         r"Found profile",
+        r"DOW2.exe started at",
+        r"MB Physical Memory",
+        r"RUN OPTIONS",
+        r"Initializing mod",
+        r"XTHREAD: Detected",
+        r"SchedulePeerForDestruction - info",
+        r"remote station \[\d+\] was disconnected",
     ])
     .unwrap();
+    static ref LINE_TIME_REGEXP: Regex = Regex::new(r"^(\d{2}:\d{2}:\d{2}\.\d+)").unwrap();
     static ref GAME_START_REGEXP: RegexSet = RegexSet::new(MATCH_BLOCK_PATTERNS).unwrap();
+    /// Lines with the players and results of a game, the report is wrong without any of them
+    static ref REPORT_LINE_REGEXP: RegexSet = RegexSet::new([
+        r"Match Started",
+        r"PlayerInfo - SimID",
+        r"ReportSimStats",
+    ])
+    .unwrap();
 }
 
 #[derive(Clone, Default, Debug, Deserialize, Serialize)]
@@ -45,10 +77,82 @@ pub struct LogfileGameInfo {
     pub id: usize,
     pub map: String,
     pub frames: usize,
+    pub started_at: String,
     pub ended_at: String,
     pub winner: u8,
     pub players: Vec<LogfilePlayerInfo>,
     pub complete: bool,
+    pub humans: usize,
+    pub computers: usize,
+    pub steam_id: usize, // Steam profile that was logged in when the game started
+    pub session: SessionInfo,
+    /// Everybody who left before the game ended, in the order it happened
+    pub leaver_timeline: Vec<LeaverEvent>,
+    /// Lines of this game that lenient parsing had to skip, the game is incomplete if there are any
+    #[serde(default)]
+    pub diagnostics: Vec<ParseDiagnostic>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaveKind {
+    /// The connection to the player's station was lost
+    Disconnected,
+    /// The player gave up, the game reports this with the results
+    Conceded,
+    /// The game removed the player and handed their army to the AI
+    Dropped,
+    /// The player's simulation diverged, usually after a rage quit or a bad connection
+    OutOfSync,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LeaverEvent {
+    pub kind: LeaveKind,
+    pub at: String,
+    pub frame: Option<usize>,
+    pub game_time_secs: Option<usize>,
+    /// Network station of the player. Disconnects only know the station until the drop that
+    /// follows tells the player.
+    pub station: Option<usize>,
+    pub sim_id: Option<usize>,
+    /// Reason code of disconnects as logged by the game. What the codes mean is not known.
+    pub reason_code: Option<usize>,
+}
+
+impl LeaverEvent {
+    fn new(kind: LeaveKind, at: Option<String>) -> Self {
+        Self {
+            kind,
+            at: at.unwrap_or_default(),
+            frame: None,
+            game_time_secs: None,
+            station: None,
+            sim_id: None,
+            reason_code: None,
+        }
+    }
+
+    fn at_frame(mut self, frame: usize) -> Self {
+        self.frame = Some(frame);
+        self.game_time_secs = Some(frame / SIM_FRAMES_PER_SECOND);
+        self
+    }
+}
+
+/// What the head of warnings.txt tells about the running game client. Every game of the logfile
+/// was played in this session.
+#[derive(Clone, Default, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SessionInfo {
+    pub executable: String,
+    pub started_at: String,
+    pub os: String,
+    pub physical_memory_mb: usize,
+    pub run_options: Vec<String>,
+    pub mod_name: String,
+    pub mod_version: String,
+    pub cpu_cores: usize,
+    pub hardware_threads: usize,
 }
 
 impl LogfileGameInfo {
@@ -59,13 +163,144 @@ impl LogfileGameInfo {
     pub fn block_complete(&self) -> bool {
         self.complete
     }
+
+    /// Custom and unranked automatch games report a ranking of -1 for every player in the
+    /// `Match Started` header. As soon as one player carries a ladder ranking the game was ranked.
+    pub fn is_ranked(&self) -> bool {
+        self.players.iter().any(|player| player.ranking.is_some())
+    }
+
+    /// Computer opponents were part of the game, either from the lobby or found in the results
+    pub fn has_computer_players(&self) -> bool {
+        self.computers > 0 || self.players.iter().any(|player| player.computer)
+    }
+
+    /// Every client reports a checksum of its game files after loading. Differing checksums mean
+    /// somebody played with modified files.
+    pub fn checksum_mismatch(&self) -> bool {
+        let mut checksums = self
+            .players
+            .iter()
+            .filter_map(|player| player.load_checksum);
+
+        match checksums.next() {
+            Some(first) => checksums.any(|checksum| checksum != first),
+            None => false,
+        }
+    }
+
+    /// The player who left first, the one league rules penalize. Conceding ends the game instead
+    /// of leaving it early.
+    pub fn first_leaver(&self) -> Option<&LeaverEvent> {
+        self.leaver_timeline
+            .iter()
+            .find(|event| event.sim_id.is_some() && event.kind != LeaveKind::Conceded)
+    }
+
+    /// Wall clock time between loading into the game and leaving it, including pauses
+    pub fn real_duration(&self) -> Option<Duration> {
+        let started_at = DateTime::parse_from_rfc3339(&self.started_at).ok()?;
+        let ended_at = DateTime::parse_from_rfc3339(&self.ended_at).ok()?;
+
+        Some(ended_at - started_at)
+    }
 }
 
 #[derive(Clone, Default, Debug, Deserialize, Serialize)]
 pub struct LogfileGameList {
     logfile_content: Vec<String>,
+    line_numbers: Vec<usize>, // 1-based line in the logfile of every entry in `logfile_content`
     pub games: Vec<LogfileGameInfo>,
-    pub steam_id: usize,
+    pub steam_id: usize, // Steam profile that is currently logged in, every game keeps its own
+    /// Every Steam profile that logged in during the session, e.g. on a shared PC at a LAN event
+    pub profiles: Vec<usize>,
+    pub diagnostics: Vec<ParseDiagnostic>,
+    pub session: SessionInfo,
+    #[serde(skip)]
+    clock: LogClock,
+    /// Station of the drop announced by the line before the `GAME -- Frame` drop line
+    #[serde(skip)]
+    dropping_station: Option<usize>,
+}
+
+/// Log lines only carry a time of day. The header line tells the local date and time the game was
+/// started at, which anchors the line times to absolute dates in the local time zone.
+#[derive(Clone, Debug, Default)]
+struct LogClock {
+    date: Option<chrono::NaiveDate>,
+    offset: Option<FixedOffset>,
+    last_time: Option<NaiveTime>,
+}
+
+impl LogClock {
+    fn start(&mut self, started_at: NaiveDateTime, line_time: NaiveTime) {
+        // The log clock runs behind the local time by the time zone offset. The header only has
+        // minutes, so round to the nearest time zone.
+        let mut offset_secs = (started_at.time() - line_time).num_seconds();
+        if offset_secs > SECONDS_PER_DAY / 2 {
+            offset_secs -= SECONDS_PER_DAY;
+        } else if offset_secs < -SECONDS_PER_DAY / 2 {
+            offset_secs += SECONDS_PER_DAY;
+        }
+        let offset_secs = (offset_secs as f64 / TIMEZONE_GRANULARITY_SECS as f64).round() as i64
+            * TIMEZONE_GRANULARITY_SECS;
+
+        let Some(offset) = FixedOffset::east_opt(offset_secs as i32) else {
+            return;
+        };
+
+        // Date of the log clock, which differs from the local date around midnight
+        let log_started_at = started_at - Duration::seconds(offset_secs);
+        let mut date = log_started_at.date();
+        let drift = date.and_time(line_time) - log_started_at;
+        if drift > Duration::hours(12) {
+            date = date.pred_opt().unwrap_or(date);
+        } else if drift < Duration::hours(-12) {
+            date = date.succ_opt().unwrap_or(date);
+        }
+
+        self.date = Some(date);
+        self.offset = Some(offset);
+        self.last_time = Some(line_time);
+    }
+
+    fn timestamp(&mut self, line_time: NaiveTime) -> Option<DateTime<FixedOffset>> {
+        let offset = self.offset?;
+        let mut date = self.date?;
+
+        // Times only ever move forward within a logfile, a step back means midnight passed
+        if self
+            .last_time
+            .is_some_and(|last_time| line_time < last_time)
+        {
+            date = date.succ_opt()?;
+            self.date = Some(date);
+        }
+        self.last_time = Some(line_time);
+
+        Some(offset.from_utc_datetime(&date.and_time(line_time)))
+    }
+}
+
+/// A line `parse_lenient` could not make sense of and skipped
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ParseDiagnostic {
+    pub line_no: usize,
+    pub line: String,
+    pub reason: String,
+}
+
+impl ParseDiagnostic {
+    /// Whether the skipped line carried players or results the report is built from
+    pub fn affects_report(&self) -> bool {
+        REPORT_LINE_REGEXP.is_match(&self.line)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ParseMode {
+    Strict,
+    Lenient,
 }
 
 #[derive(Debug, Default)]
@@ -73,6 +308,13 @@ pub struct SteamIdMap {
     relic_id: usize,
     slot: usize,
     uid: String, // Game internal user id per player that is assigned when the match starts. Will be used to identify dropped players.
+    ranking: Option<i32>,
+    load_started_at: Option<NaiveTime>,
+    load_checksum: Option<i64>,
+    load_time_ms: Option<u64>,
+    /// The game repeats the `finished loading` line until everybody is done, only the first one
+    /// counts
+    load_finished: bool,
 }
 
 #[derive(Debug)]
@@ -93,28 +335,76 @@ impl LogfileGameList {
             return Err(ParserAppError::LogfileNotFoundError);
         }
 
-        // Rust can not directly read from this file since it is not UTF-8 encoded
-        let logfile = File::open(logfilepath)?;
-        let mut reader = DecodeReaderBytesBuilder::new()
-            .encoding(Some(encoding_rs::UTF_8))
-            .build(logfile);
-
-        let mut buffer = vec![];
-
-        reader.read_to_end(&mut buffer)?;
-
-        let result = String::from_utf8(buffer)?
-            .lines()
-            .map(|line| line.to_string())
-            .filter(|line| contains_desired_content(line))
-            .collect::<Vec<_>>();
+        let (line_numbers, result): (Vec<_>, Vec<_>) = read_lines(logfilepath)?
+            .into_iter()
+            .enumerate()
+            .filter(|(_, line)| contains_desired_content(line))
+            .map(|(index, line)| (index + 1, line))
+            .unzip();
 
         self.logfile_content = result;
+        self.line_numbers = line_numbers;
         tracing::debug!("Done reading logfile");
         Ok(())
     }
 
+    /// Unfiltered lines of the last game in the logfile, from the end of the previous game up to
+    /// the end of the file. This is the excerpt needed to reproduce a failing parse.
+    pub fn last_game_block(logfilepath: &Path) -> ParserAppResult<Vec<String>> {
+        if !logfilepath.exists() {
+            return Err(ParserAppError::LogfileNotFoundError);
+        }
+
+        let lines = read_lines(logfilepath)?;
+        let Some(mission_start) = lines
+            .iter()
+            .rposition(|line| line.contains("Beginning mission"))
+        else {
+            return Ok(vec![]);
+        };
+
+        // The `Match Started` header may be logged before `Beginning mission`
+        let block_start = lines[..mission_start]
+            .iter()
+            .rposition(|line| line.contains("Ending mission"))
+            .map_or(0, |index| index + 1);
+
+        Ok(lines[block_start..].to_vec())
+    }
+
+    /// The game whose end is closest to `time`, e.g. the modification time of `temp.rec`, as long as
+    /// it ended within `tolerance` of it
+    pub fn game_ended_near(
+        &self,
+        time: DateTime<Utc>,
+        tolerance: Duration,
+    ) -> Option<&LogfileGameInfo> {
+        self.games
+            .iter()
+            .filter_map(|game| {
+                let ended_at = DateTime::parse_from_rfc3339(&game.ended_at).ok()?;
+                let distance = (ended_at.with_timezone(&Utc) - time).abs();
+
+                (distance <= tolerance).then_some((distance, game))
+            })
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, game)| game)
+    }
+
+    /// Parses all games and fails on the first line that does not look as expected
     pub fn parse(&mut self) -> ParserAppResult<()> {
+        self.parse_with(ParseMode::Strict)
+    }
+
+    /// Parses all games it can. Lines that do not look as expected are skipped and recorded in
+    /// `diagnostics`, so a single odd line does not cost every game of the session. Every game
+    /// also keeps the diagnostics of its own lines, lines between two games count towards the
+    /// next one.
+    pub fn parse_lenient(&mut self) -> ParserAppResult<()> {
+        self.parse_with(ParseMode::Lenient)
+    }
+
+    fn parse_with(&mut self, mode: ParseMode) -> ParserAppResult<()> {
         tracing::debug!("Parsing logfile");
         let regexes = GAME_START_REGEXP
             .patterns()
@@ -123,279 +413,574 @@ impl LogfileGameList {
             .collect::<Vec<_>>();
 
         let mut match_header: HashMap<usize, SteamIdMap> = HashMap::new();
-
-        for line in self.logfile_content.iter() {
-            let matches = GAME_START_REGEXP.matches(line);
-            let match_captures = matches
-                .into_iter()
-                .map(|match_index| {
-                    let pat = &regexes[match_index];
-                    let captures = pat.captures(line).unwrap();
-
-                    MatchGroup {
-                        index: match_index,
-                        captures,
-                    }
-                })
-                .collect::<Vec<_>>();
-
-            if match_captures.len() != 1 {
-                tracing::error!("Found more than 1 game to parse");
-                return Err(ParserAppError::LogfileParseError(
-                    "Found more than 1 game to parse".into(),
-                ));
+        self.diagnostics.clear();
+        self.clock = LogClock::default();
+        self.dropping_station = None;
+        self.steam_id = 0;
+        self.profiles.clear();
+        self.session = SessionInfo::default();
+
+        // Lines are handed to `parse_line` which needs the rest of `self` mutably
+        let logfile_content = std::mem::take(&mut self.logfile_content);
+        let mut result = Ok(());
+        // Diagnostics from before the game they belong to has started
+        let mut pending_diagnostics = vec![];
+
+        for (index, line) in logfile_content.iter().enumerate() {
+            let game_count = self.games.len();
+            let parsed = self.parse_line(line, &regexes, &mut match_header);
+
+            if self.games.len() > game_count {
+                if let Some(game) = self.games.last_mut() {
+                    game.diagnostics.append(&mut pending_diagnostics);
+                }
             }
 
-            let match_group = &match_captures[0];
+            let Err(reason) = parsed else {
+                continue;
+            };
 
-            match match_group.index {
-                0 => {
-                    let Some(uid) = match_group.captures.get(1) else {
-                        tracing::error!("Could not parse user id from match header block");
-                        return Err(ParserAppError::LogfileParseError(
-                            "Could not parse user id from match header block".into(),
-                        ));
+            match mode {
+                ParseMode::Strict => {
+                    result = Err(ParserAppError::LogfileParseError(reason));
+                    break;
+                }
+                ParseMode::Lenient => {
+                    tracing::warn!("Skipping logfile line: {reason}");
+                    let diagnostic = ParseDiagnostic {
+                        line_no: self.line_numbers.get(index).copied().unwrap_or(index + 1),
+                        line: line.clone(),
+                        reason,
                     };
 
-                    let Some(steam_id) = match_group.captures.get(2) else {
-                        tracing::error!("Could not parse steam id from match header block");
-                        return Err(ParserAppError::LogfileParseError(
-                            "Could not parse steam id from match header block".into(),
-                        ));
-                    };
+                    match self.game_in_progress() {
+                        Some(game) => game.diagnostics.push(diagnostic.clone()),
+                        None => pending_diagnostics.push(diagnostic.clone()),
+                    }
+                    self.diagnostics.push(diagnostic);
+                }
+            }
+        }
 
-                    let Some(slot) = match_group.captures.get(3) else {
-                        tracing::error!("Could not parse slot number from match header block");
-                        return Err(ParserAppError::LogfileParseError(
-                            "Could not parse slot number from match header block".into(),
-                        ));
-                    };
+        self.logfile_content = logfile_content;
+        result
+    }
 
-                    let uid = uid.as_str().into();
-                    let steam_id = steam_id.as_str().parse::<usize>().unwrap_or_default();
-                    let slot = slot.as_str().parse::<usize>().unwrap_or_default();
+    /// Leaving players are only of interest between the start and the end of a game
+    fn game_in_progress(&mut self) -> Option<&mut LogfileGameInfo> {
+        self.games.last_mut().filter(|game| !game.complete)
+    }
 
-                    match_header.insert(
-                        steam_id,
-                        SteamIdMap {
-                            relic_id: 0,
-                            slot,
-                            uid,
-                        },
-                    );
+    fn parse_line(
+        &mut self,
+        line: &str,
+        regexes: &[Regex],
+        match_header: &mut HashMap<usize, SteamIdMap>,
+    ) -> Result<(), String> {
+        let line_time = LINE_TIME_REGEXP
+            .captures(line)
+            .and_then(|captures| NaiveTime::parse_from_str(&captures[1], "%H:%M:%S%.f").ok());
+        let timestamp = line_time
+            .and_then(|line_time| self.clock.timestamp(line_time))
+            .map(|timestamp| timestamp.to_rfc3339_opts(SecondsFormat::Secs, false));
+
+        let matches = GAME_START_REGEXP.matches(line);
+        let match_captures = matches
+            .into_iter()
+            .map(|match_index| {
+                let pat = &regexes[match_index];
+                let captures = pat.captures(line).unwrap();
+
+                MatchGroup {
+                    index: match_index,
+                    captures,
                 }
-                1 => {
-                    // Is there any game in the list to begin with
-                    if let Some(last_game) = self.games.last_mut() {
-                        // We can't know whether game block was created with capture index 0 or 1
-                        // -> check if last game block is complete, i.e. Ending mission was read
-                        match last_game.block_complete() {
-                            true => {
-                                self.games.push(LogfileGameInfo::new());
-                            }
-                            false => (),
-                        }
-                    } else {
-                        self.games.push(LogfileGameInfo::new());
-                    }
+            })
+            .collect::<Vec<_>>();
 
-                    // At this point there should be a game in the list
-                    let Some(map) = match_group.captures.get(1) else {
-                        tracing::error!("Could not parse map from logfile");
-                        return Err(ParserAppError::LogfileParseError(
-                            "Could not parse map from logfile".into(),
-                        ));
-                    };
+        if match_captures.len() != 1 {
+            tracing::error!(
+                "Line matches {} patterns instead of 1",
+                match_captures.len()
+            );
+            return Err(format!(
+                "Line matches {} patterns instead of 1",
+                match_captures.len()
+            ));
+        }
 
-                    let len = self.games.len() - 1;
-                    self.games[len].map = map.as_str().to_string();
-                }
-                2 => (),
-                3 => {
-                    if self.games.last_mut().is_some() {
-                        let mut player = LogfilePlayerInfo::new();
-                        player.parse(&match_group.captures, false);
-
-                        // Add slot number and steam id from hashmap
-                        let steam_id = match_header.iter().find_map(|(key, val)| {
-                            if val.relic_id == player.relic_id {
-                                Some(key)
-                            } else {
-                                None
-                            }
-                        });
-                        if let Some(steam_id) = steam_id {
-                            player.steam_id = *steam_id;
-                            player.slot = match_header.get(steam_id).unwrap().slot;
+        let match_group = &match_captures[0];
+
+        match match_group.index {
+            0 => {
+                let Some(uid) = match_group.captures.get(1) else {
+                    tracing::error!("Could not parse user id from match header block");
+                    return Err("Could not parse user id from match header block".into());
+                };
+
+                let Some(steam_id) = match_group.captures.get(2) else {
+                    tracing::error!("Could not parse steam id from match header block");
+                    return Err("Could not parse steam id from match header block".into());
+                };
+
+                let Some(slot) = match_group.captures.get(3) else {
+                    tracing::error!("Could not parse slot number from match header block");
+                    return Err("Could not parse slot number from match header block".into());
+                };
+
+                let uid = uid.as_str().into();
+                let steam_id = steam_id.as_str().parse::<usize>().unwrap_or_default();
+                let slot = slot.as_str().parse::<usize>().unwrap_or_default();
+                // Older logfiles do not carry the ranking and unranked players report -1
+                let ranking = match_group
+                    .captures
+                    .get(4)
+                    .and_then(|ranking| ranking.as_str().parse::<i32>().ok())
+                    .filter(|ranking| *ranking >= 0);
+
+                match_header.insert(
+                    steam_id,
+                    SteamIdMap {
+                        relic_id: 0,
+                        slot,
+                        uid,
+                        ranking,
+                        ..Default::default()
+                    },
+                );
+            }
+            1 => {
+                // Is there any game in the list to begin with
+                if let Some(last_game) = self.games.last_mut() {
+                    // We can't know whether game block was created with capture index 0 or 1
+                    // -> check if last game block is complete, i.e. Ending mission was read
+                    match last_game.block_complete() {
+                        true => {
+                            self.games.push(LogfileGameInfo::new());
                         }
+                        false => (),
+                    }
+                } else {
+                    self.games.push(LogfileGameInfo::new());
+                }
 
-                        if let Some(last_game) = self.games.last_mut() {
-                            last_game.players.push(player);
-                        }
+                // At this point there should be a game in the list
+                let Some(map) = match_group.captures.get(1) else {
+                    tracing::error!("Could not parse map from logfile");
+                    return Err("Could not parse map from logfile".into());
+                };
+
+                let humans = match_group.captures.get(2).map_or(0, |humans| {
+                    humans.as_str().parse::<usize>().unwrap_or_default()
+                });
+                let computers = match_group.captures.get(3).map_or(0, |computers| {
+                    computers.as_str().parse::<usize>().unwrap_or_default()
+                });
+
+                let len = self.games.len() - 1;
+                self.games[len].map = map.as_str().to_string();
+                self.games[len].humans = humans;
+                self.games[len].computers = computers;
+                self.games[len].steam_id = self.steam_id;
+                self.games[len].started_at = timestamp.unwrap_or_default();
+                self.games[len].session = self.session.clone();
+            }
+            2 => {
+                let (Some(frame), Some(sim_id)) =
+                    (match_group.captures.get(1), match_group.captures.get(2))
+                else {
+                    return Ok(());
+                };
+
+                let (Ok(frame), Ok(sim_id)) = (
+                    frame.as_str().parse::<usize>(),
+                    sim_id.as_str().parse::<usize>(),
+                ) else {
+                    return Err("Could not parse dropped player from logfile".into());
+                };
+
+                let station = self.dropping_station.take();
+                let Some(game) = self.game_in_progress() else {
+                    return Ok(());
+                };
+
+                // Disconnects of the station happened before and now know their player
+                for event in game.leaver_timeline.iter_mut() {
+                    if event.station.is_some() && event.station == station && event.sim_id.is_none()
+                    {
+                        event.sim_id = Some(sim_id);
                     }
                 }
-                4 => {
-                    if self.games.last_mut().is_some() {
-                        let mut player = LogfilePlayerInfo::new();
-                        player.parse(&match_group.captures, true);
-
-                        // This match result line does not contain the players relic id but his game internal user id
-                        let uid = match_group.captures.get(4).unwrap().as_str().to_string();
-
-                        // Add slot number and steam id from hashmap
-                        let steam_id = match_header.iter().find_map(|(key, val)| {
-                            if val.uid == uid {
-                                Some(key)
-                            } else {
-                                None
-                            }
-                        });
-                        if let Some(steam_id) = steam_id {
-                            player.steam_id = *steam_id;
-                            player.slot = match_header.get(steam_id).unwrap().slot;
-                            player.relic_id = match_header.get(steam_id).unwrap().relic_id;
-                        }
 
-                        if let Some(last_game) = self.games.last_mut() {
-                            last_game.players.push(player);
+                let mut event = LeaverEvent::new(LeaveKind::Dropped, timestamp).at_frame(frame);
+                event.station = station;
+                event.sim_id = Some(sim_id);
+                game.leaver_timeline.push(event);
+            }
+            3 => {
+                if self.games.last_mut().is_some() {
+                    let mut player = LogfilePlayerInfo::new();
+                    player.parse(&match_group.captures, false);
+
+                    // Add slot number and steam id from hashmap
+                    let steam_id = match_header.iter().find_map(|(key, val)| {
+                        if val.relic_id == player.relic_id {
+                            Some(key)
+                        } else {
+                            None
                         }
+                    });
+                    if let Some(steam_id) = steam_id {
+                        player.steam_id = *steam_id;
+                        player.slot = match_header.get(steam_id).unwrap().slot;
+                        player.ranking = match_header.get(steam_id).unwrap().ranking;
+                        player.load_checksum = match_header.get(steam_id).unwrap().load_checksum;
+                        player.load_time_ms = match_header.get(steam_id).unwrap().load_time_ms;
                     }
-                }
-                5 => {
-                    if let Some(last_game) = self.games.last_mut() {
-                        let Some(match_relic_id) = match_group.captures.get(1) else {
-                            tracing::error!("Could not extract match relic id from logfile");
-                            return Err(ParserAppError::LogfileParseError(
-                                "Could not extract match relic id from logfile".into(),
-                            ));
-                        };
 
-                        let Ok(match_relic_id) = match_relic_id.as_str().parse::<usize>() else {
-                            tracing::error!("Could not parse match relic id in logfile");
-                            return Err(ParserAppError::LogfileParseError(
-                                "Could not parse match relic id in logfile".into(),
-                            ));
+                    if let Some(last_game) = self.games.last_mut() {
+                        // Results are only logged once the game is over, when the player left is
+                        // not known
+                        let kind = match player.status {
+                            LogfilePlayerStatus::Outofsync => Some(LeaveKind::OutOfSync),
+                            LogfilePlayerStatus::Conceded => Some(LeaveKind::Conceded),
+                            _ => None,
                         };
+                        if let Some(kind) = kind {
+                            let mut event = LeaverEvent::new(kind, timestamp.clone());
+                            event.sim_id = Some(player.sim_id);
+                            last_game.leaver_timeline.push(event);
+                        }
 
-                        last_game.id = match_relic_id;
+                        last_game.players.push(player);
                     }
                 }
-                6 => {
-                    if let Some(last_game) = self.games.last_mut() {
-                        // Get game ending status
-                        match match_group.captures.get(1) {
-                            Some(capture) => match capture.as_str() {
-                                "Game over" => {
-                                    tracing::debug!("Game ended regularly");
-                                    last_game.aborted = false;
-                                    last_game.complete = true;
-                                }
-                                "Abort" => {
-                                    tracing::debug!("Game was aborted");
-                                    last_game.aborted = true;
-                                    last_game.complete = true;
-                                }
-                                // Unknown status - defaulting to a cancelled and complete game
-                                status => {
-                                    tracing::error!("Unknown game status found: {:?}. Setting game status to aborted. Completing", status);
-                                    last_game.aborted = true;
-                                    last_game.complete = true;
+            }
+            4 => {
+                if self.games.last_mut().is_some() {
+                    let mut player = LogfilePlayerInfo::new();
+                    player.parse(&match_group.captures, true);
+
+                    // This match result line does not contain the players relic id but his game internal user id
+                    let uid = match_group.captures.get(4).unwrap().as_str().to_string();
+
+                    // Add slot number and steam id from hashmap
+                    let steam_id =
+                        match_header.iter().find_map(
+                            |(key, val)| {
+                                if val.uid == uid {
+                                    Some(key)
+                                } else {
+                                    None
                                 }
                             },
-                            // Could not find the game ending status information - defaulting to a
-                            // cancelled and complete game
-                            None => {
-                                tracing::error!("No game status found. Setting game status to aborted. Completing");
-                                last_game.aborted = true;
-                                last_game.complete = true;
-                            }
-                        }
+                        );
+                    if let Some(steam_id) = steam_id {
+                        player.steam_id = *steam_id;
+                        player.slot = match_header.get(steam_id).unwrap().slot;
+                        player.relic_id = match_header.get(steam_id).unwrap().relic_id;
+                        player.ranking = match_header.get(steam_id).unwrap().ranking;
+                        player.load_checksum = match_header.get(steam_id).unwrap().load_checksum;
+                        player.load_time_ms = match_header.get(steam_id).unwrap().load_time_ms;
+                    } else {
+                        // Humans that dropped are taken over by the AI and logged the same way,
+                        // only computer opponents are missing from the match header
+                        player.computer = true;
+                        player.status = LogfilePlayerStatus::Unknown;
                     }
-                }
-                7 => {
-                    if let Some(last_game) = self.games.last_mut() {
-                        let Some(frames) = match_group.captures.get(1) else {
-                            tracing::error!("Could not extract number of frames from logfile");
-                            return Err(ParserAppError::LogfileParseError(
-                                "Could not extract number of frames from logfile".into(),
-                            ));
-                        };
-
-                        let Ok(frames) = frames.as_str().parse::<usize>() else {
-                            tracing::error!("Could not parse number of frames from logfile");
-                            return Err(ParserAppError::LogfileParseError(
-                                "Could not parse number of frames from logfile".into(),
-                            ));
-                        };
 
-                        tracing::debug!("Found {:?} frames in logfile", frames);
-
-                        last_game.frames = frames;
+                    if let Some(last_game) = self.games.last_mut() {
+                        last_game.players.push(player);
                     }
                 }
-                8 => {
-                    let Some(relic_id) = match_group.captures.get(1) else {
-                        tracing::error!("Could not parse relic id from logfile");
-                        return Err(ParserAppError::LogfileParseError(
-                            "Could not parse relic id from logfile".into(),
-                        ));
+            }
+            5 => {
+                if let Some(last_game) = self.games.last_mut() {
+                    let Some(match_relic_id) = match_group.captures.get(1) else {
+                        tracing::error!("Could not extract match relic id from logfile");
+                        return Err("Could not extract match relic id from logfile".into());
                     };
 
-                    let Some(steam_id) = match_group.captures.get(2) else {
-                        tracing::error!("Could not parse steam id from logfile");
-                        return Err(ParserAppError::LogfileParseError(
-                            "Could not parse steam id from logfile".into(),
-                        ));
+                    let Ok(match_relic_id) = match_relic_id.as_str().parse::<usize>() else {
+                        tracing::error!("Could not parse match relic id in logfile");
+                        return Err("Could not parse match relic id in logfile".into());
                     };
 
-                    let steam_id = steam_id.as_str().parse::<usize>().unwrap();
-                    let relic_id = relic_id.as_str().parse::<usize>().unwrap();
-
-                    if let Some(info) = match_header.get_mut(&steam_id) {
-                        info.relic_id = relic_id;
-                    } else {
-                        match_header.insert(
-                            steam_id,
-                            SteamIdMap {
-                                relic_id,
-                                ..Default::default()
-                            },
-                        );
+                    last_game.id = match_relic_id;
+                }
+            }
+            6 => {
+                if let Some(last_game) = self.games.last_mut() {
+                    last_game.ended_at = timestamp.unwrap_or_default();
+
+                    // Get game ending status
+                    match match_group.captures.get(1) {
+                        Some(capture) => match capture.as_str() {
+                            "Game over" => {
+                                tracing::debug!("Game ended regularly");
+                                last_game.aborted = false;
+                                last_game.complete = true;
+                            }
+                            "Abort" => {
+                                tracing::debug!("Game was aborted");
+                                last_game.aborted = true;
+                                last_game.complete = true;
+                            }
+                            // Unknown status - defaulting to a cancelled and complete game
+                            status => {
+                                tracing::error!("Unknown game status found: {:?}. Setting game status to aborted. Completing", status);
+                                last_game.aborted = true;
+                                last_game.complete = true;
+                            }
+                        },
+                        // Could not find the game ending status information - defaulting to a
+                        // cancelled and complete game
+                        None => {
+                            tracing::error!(
+                                "No game status found. Setting game status to aborted. Completing"
+                            );
+                            last_game.aborted = true;
+                            last_game.complete = true;
+                        }
                     }
                 }
-                9 => {
-                    let Some(steam_id) = match_group.captures.get(1) else {
-                        tracing::error!("Could not find player profile steam id in logfile");
-                        return Err(ParserAppError::LogfileParseError(
-                            "could not find player profile steam id in logfile".into(),
-                        ));
+            }
+            7 => {
+                if let Some(last_game) = self.games.last_mut() {
+                    let Some(frames) = match_group.captures.get(1) else {
+                        tracing::error!("Could not extract number of frames from logfile");
+                        return Err("Could not extract number of frames from logfile".into());
                     };
 
-                    tracing::debug!("Found players steam profile: /steam/{:?}", steam_id);
+                    let Ok(frames) = frames.as_str().parse::<usize>() else {
+                        tracing::error!("Could not parse number of frames from logfile");
+                        return Err("Could not parse number of frames from logfile".into());
+                    };
 
-                    if let Ok(steam_id) = steam_id.as_str().parse::<usize>() {
-                        self.steam_id = steam_id;
-                    } else {
-                        tracing::error!("Could not read player profile steam id from logfile");
-                        return Err(ParserAppError::LogfileParseError(
-                            "could not read player profile steam id from logfile".into(),
-                        ));
+                    tracing::debug!("Found {:?} frames in logfile", frames);
+
+                    last_game.frames = frames;
+                }
+            }
+            8 => {
+                let Some(relic_id) = match_group.captures.get(1) else {
+                    tracing::error!("Could not parse relic id from logfile");
+                    return Err("Could not parse relic id from logfile".into());
+                };
+
+                let Some(steam_id) = match_group.captures.get(2) else {
+                    tracing::error!("Could not parse steam id from logfile");
+                    return Err("Could not parse steam id from logfile".into());
+                };
+
+                let Ok(steam_id) = steam_id.as_str().parse::<usize>() else {
+                    return Err(format!("Steam id {:?} is not a number", steam_id.as_str()));
+                };
+                let Ok(relic_id) = relic_id.as_str().parse::<usize>() else {
+                    return Err(format!("Relic id {:?} is not a number", relic_id.as_str()));
+                };
+
+                let info = match_header.entry(steam_id).or_default();
+                info.relic_id = relic_id;
+
+                let checksum = match_group
+                    .captures
+                    .get(4)
+                    .and_then(|checksum| checksum.as_str().parse::<i64>().ok());
+
+                match match_group
+                    .captures
+                    .get(3)
+                    .map(|progress| progress.as_str())
+                {
+                    // Reset what might be left over from the previous game
+                    Some("has started") => {
+                        info.load_started_at = line_time;
+                        info.load_checksum = None;
+                        info.load_time_ms = None;
+                        info.load_finished = false;
+                    }
+                    Some("finished") if !info.load_finished => {
+                        info.load_finished = true;
+                        info.load_checksum = checksum;
+                        info.load_time_ms = info
+                            .load_started_at
+                            .zip(line_time)
+                            .map(|(started_at, finished_at)| {
+                                // Loading across midnight wraps the log clock
+                                let mut load_time = finished_at - started_at;
+                                if load_time < Duration::zero() {
+                                    load_time += Duration::days(1);
+                                }
+                                load_time
+                            })
+                            .and_then(|load_time| u64::try_from(load_time.num_milliseconds()).ok());
                     }
+                    _ => (),
                 }
-                capture_group => {
-                    tracing::error!("RegEx error while parsing logfile: {:?}", capture_group);
-                    return Err(ParserAppError::LogfileParseError(format!(
-                        "RegEx error while parsing logfile: {}",
-                        capture_group
-                    )));
+            }
+            9 => {
+                let Some(steam_id) = match_group.captures.get(1) else {
+                    tracing::error!("Could not find player profile steam id in logfile");
+                    return Err("could not find player profile steam id in logfile".into());
+                };
+
+                tracing::debug!("Found players steam profile: /steam/{:?}", steam_id);
+
+                if let Ok(steam_id) = steam_id.as_str().parse::<usize>() {
+                    self.steam_id = steam_id;
+                    if !self.profiles.contains(&steam_id) {
+                        self.profiles.push(steam_id);
+                    }
+                } else {
+                    tracing::error!("Could not read player profile steam id from logfile");
+                    return Err("could not read player profile steam id from logfile".into());
                 }
             }
+            10 => {
+                let Some(executable) = match_group.captures.get(1) else {
+                    return Err("Could not find executable in logfile header".into());
+                };
+
+                let Some(started_at) = match_group.captures.get(2) else {
+                    return Err("Could not find game start time in logfile header".into());
+                };
+
+                let Ok(started_at) =
+                    NaiveDateTime::parse_from_str(started_at.as_str(), "%Y-%m-%d %H:%M")
+                else {
+                    return Err(format!(
+                        "Game start time {:?} is not a date",
+                        started_at.as_str()
+                    ));
+                };
+
+                let Some(line_time) = line_time else {
+                    return Err("Logfile header has no line time".into());
+                };
+
+                self.clock.start(started_at, line_time);
+
+                // A new header starts a new session
+                self.session = SessionInfo {
+                    executable: executable.as_str().into(),
+                    started_at: self
+                        .clock
+                        .timestamp(line_time)
+                        .map(|timestamp| timestamp.to_rfc3339_opts(SecondsFormat::Secs, false))
+                        .unwrap_or_default(),
+                    ..Default::default()
+                };
+            }
+            11 => {
+                let (Some(os), Some(memory)) =
+                    (match_group.captures.get(1), match_group.captures.get(2))
+                else {
+                    return Err("Could not parse system information from logfile header".into());
+                };
+
+                self.session.os = os.as_str().into();
+                self.session.physical_memory_mb = memory.as_str().parse().unwrap_or_default();
+            }
+            12 => {
+                let Some(run_options) = match_group.captures.get(1) else {
+                    return Err("Could not parse run options from logfile header".into());
+                };
+
+                self.session.run_options = run_options
+                    .as_str()
+                    .split_whitespace()
+                    .map(String::from)
+                    .collect();
+            }
+            13 => {
+                let (Some(mod_name), Some(mod_version)) =
+                    (match_group.captures.get(1), match_group.captures.get(2))
+                else {
+                    return Err("Could not parse mod from logfile header".into());
+                };
+
+                tracing::debug!(
+                    "Found mod {:?} version {:?}",
+                    mod_name.as_str(),
+                    mod_version.as_str()
+                );
+
+                self.session.mod_name = mod_name.as_str().into();
+                self.session.mod_version = mod_version.as_str().into();
+            }
+            14 => {
+                let (Some(cores), Some(threads)) =
+                    (match_group.captures.get(1), match_group.captures.get(2))
+                else {
+                    return Err("Could not parse CPU information from logfile header".into());
+                };
+
+                self.session.cpu_cores = cores.as_str().parse().unwrap_or_default();
+                self.session.hardware_threads = threads.as_str().parse().unwrap_or_default();
+            }
+            15 => {
+                let Some(station) = match_group.captures.get(1) else {
+                    return Err("Could not parse station of dropped player from logfile".into());
+                };
+
+                self.dropping_station = station.as_str().parse::<usize>().ok();
+            }
+            16 => {
+                let (Some(station), Some(reason_code)) =
+                    (match_group.captures.get(1), match_group.captures.get(2))
+                else {
+                    return Err("Could not parse disconnected station from logfile".into());
+                };
+
+                let station = station.as_str().parse::<usize>().ok();
+                let Some(game) = self.game_in_progress() else {
+                    return Ok(());
+                };
+
+                // The station may have been dropped already
+                let sim_id = game
+                    .leaver_timeline
+                    .iter()
+                    .find(|event| event.station.is_some() && event.station == station)
+                    .and_then(|event| event.sim_id);
+
+                let mut event = LeaverEvent::new(LeaveKind::Disconnected, timestamp);
+                event.station = station;
+                event.sim_id = sim_id;
+                event.reason_code = reason_code.as_str().parse::<usize>().ok();
+                game.leaver_timeline.push(event);
+            }
+            capture_group => {
+                tracing::error!("RegEx error while parsing logfile: {:?}", capture_group);
+                return Err(format!(
+                    "RegEx error while parsing logfile: {}",
+                    capture_group
+                ));
+            }
         }
 
         Ok(())
     }
 }
 
+fn read_lines(logfilepath: &Path) -> ParserAppResult<Vec<String>> {
+    // Rust can not directly read from this file since it is not UTF-8 encoded
+    let logfile = File::open(logfilepath)?;
+    let mut reader = DecodeReaderBytesBuilder::new()
+        .encoding(Some(encoding_rs::UTF_8))
+        .build(logfile);
+
+    let mut buffer = vec![];
+
+    reader.read_to_end(&mut buffer)?;
+
+    Ok(String::from_utf8(buffer)?
+        .lines()
+        .map(|line| line.to_string())
+        .collect())
+}
+
 fn contains_desired_content(string: &str) -> bool {
     LOGFILE_FILTER_REGEXP
         .matches(string)
@@ -407,7 +992,9 @@ fn contains_desired_content(string: &str) -> bool {
 #[cfg(test)]
 mod tests {
 
-    use crate::core::{logfile::LogfileGameList, player_info::LogfilePlayerStatus};
+    use crate::core::{
+        logfile::LogfileGameList, player_info::LogfilePlayerStatus, test_support::TestDir,
+    };
 
     use super::*;
 
@@ -417,7 +1004,68 @@ mod tests {
         let mut game_list = LogfileGameList::new();
         game_list.read_logfile(logfilepath).unwrap();
 
-        assert_eq!(game_list.logfile_content.len(), 241);
+        assert_eq!(game_list.logfile_content.len(), 272);
+    }
+
+    #[test]
+    fn lenient_parsing_skips_bad_lines() {
+        // The first two games of warnings.txt with a bad line after the first game and one in the
+        // second game
+        let logfile_path = Path::new("warnings_with_bad_lines.txt");
+        let mut game_list = LogfileGameList::new();
+        game_list.read_logfile(logfile_path).unwrap();
+
+        assert!(game_list.clone().parse().is_err());
+
+        game_list.parse_lenient().unwrap();
+        assert_eq!(game_list.games.len(), 2);
+        assert_eq!(
+            game_list
+                .diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.line_no)
+                .collect::<Vec<_>>(),
+            vec![19, 27]
+        );
+        assert!(game_list.diagnostics[0]
+            .reason
+            .contains("patterns instead of 1"));
+        assert!(game_list.diagnostics[1].reason.contains("Steam id"));
+
+        assert!(game_list.games[0].diagnostics.is_empty());
+        assert_eq!(game_list.games[1].diagnostics, game_list.diagnostics);
+
+        // Neither line carries players or results, the game is reported with a warning
+        let game =
+            crate::core::parse_logfile_for_replay(logfile_path, Path::new("missing.rec")).unwrap();
+        assert_eq!(game.id, 54865677);
+        assert_eq!(game.diagnostics.len(), 2);
+
+        // A skipped result line would give the report a player less
+        let test_dir = TestDir::new("bad_result_line");
+        let broken_logfile_path = test_dir.join("warnings.txt");
+        let content = String::from_utf8_lossy(&std::fs::read(logfile_path).unwrap()).replace(
+            "uid:0:10786612, result:5:PS_WON",
+            "uid:0:10786612, result:5:PS_WON, Found profile: /steam/1",
+        );
+        std::fs::write(&broken_logfile_path, content).unwrap();
+
+        let error =
+            crate::core::parse_logfile_for_replay(&broken_logfile_path, Path::new("missing.rec"))
+                .unwrap_err();
+        assert_eq!(error.code(), "logfile_parse");
+        assert!(error.to_string().contains("line 119"));
+    }
+
+    #[test]
+    fn extracts_last_game_block() {
+        let block = LogfileGameList::last_game_block(Path::new("warnings.txt")).unwrap();
+
+        assert_eq!(block.len(), 2867);
+        assert!(block
+            .iter()
+            .any(|line| line.contains("Beginning mission 6p_hades_wastes_redux")));
+        assert!(!block.iter().any(|line| line.contains("2p_paletoothgorge")));
     }
 
     #[test]
@@ -536,5 +1184,284 @@ mod tests {
         game_list.parse().unwrap();
 
         assert_eq!(game_list.steam_id, 76561198099396483);
+        assert!(game_list
+            .games
+            .iter()
+            .all(|game| game.steam_id == 76561198099396483));
+    }
+
+    #[test]
+    fn custom_games_are_not_ranked() {
+        let logfilepath = Path::new("warnings.txt");
+        let mut game_list = LogfileGameList::new();
+        game_list.read_logfile(logfilepath).unwrap();
+        game_list.parse().unwrap();
+
+        assert!(game_list.games.iter().all(|game| !game.is_ranked()));
+        assert!(game_list.games[0]
+            .players
+            .iter()
+            .all(|player| player.ranking.is_none()));
+    }
+
+    #[test]
+    fn ranking_is_read_from_match_header() {
+        let mut game_list = LogfileGameList::new();
+        game_list.logfile_content = vec![
+            "10:27:44.27    Match Started - [00000000:009b51a6 /steam/76561197978951214], slot =  0, ranking =   12".into(),
+            "10:27:44.27    Match Started - [00000000:009b463d /steam/76561198099396483], slot =  4, ranking =   -1".into(),
+            "10:27:45.84    GAME -- *** Beginning mission 2p_calderisdunes (2 Humans, 0 Computers) ***".into(),
+            "10:27:45.97    LoadArbitrator::UpdateLoadProgress - info, player \"[sid 00000000:00000002, pid 0:10176061, /steam/76561198099396483]\" finished loading with checksum [4244482950].".into(),
+            "10:27:46.06    LoadArbitrator::UpdateLoadProgress - info, player \"[sid 00000000:00000001, pid 0:10178982, /steam/76561197978951214]\" finished loading with checksum [4244482950].".into(),
+            "10:39:01.32    MOD -- Game Over at frame 6599".into(),
+            "10:39:01.38    PlayerInfo - SimID:1001, raceID:4, teamID:0, uid:0:10178982, result:5:PS_WON".into(),
+            "10:39:01.38    PlayerInfo - SimID:1000, raceID:3, teamID:1, uid:0:10176061, result:7:PS_CONCEDED".into(),
+            "10:39:07.91    GAME -- Ending mission - 'Game over'".into(),
+        ];
+        game_list.parse().unwrap();
+
+        assert_eq!(game_list.games[0].players[0].ranking, Some(12));
+        assert_eq!(game_list.games[0].players[1].ranking, None);
+        assert!(game_list.games[0].is_ranked());
+    }
+
+    #[test]
+    fn game_times_are_anchored_to_logfile_header() {
+        let logfilepath = Path::new("warnings.txt");
+        let mut game_list = LogfileGameList::new();
+        game_list.read_logfile(logfilepath).unwrap();
+        game_list.parse().unwrap();
+
+        let game = &game_list.games[0];
+        assert_eq!(game.started_at, "2022-11-16T11:27:45+01:00");
+        assert_eq!(game.ended_at, "2022-11-16T11:39:07+01:00");
+        assert_eq!(game.real_duration(), Some(Duration::seconds(682)));
+
+        let replay_written_at = DateTime::parse_from_rfc3339("2022-11-16T10:39:10Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let matching_game = game_list
+            .game_ended_near(replay_written_at, Duration::minutes(10))
+            .unwrap();
+        assert_eq!(matching_game.ended_at, game.ended_at);
+    }
+
+    #[test]
+    fn game_times_roll_over_midnight() {
+        let mut game_list = LogfileGameList::new();
+        game_list.logfile_content = vec![
+            "22:50:00.00    E:\\DOW2.exe started at 2022-11-16 23:50".into(),
+            "22:59:00.00    GAME -- *** Beginning mission 2p_calderisdunes (2 Humans, 0 Computers) ***".into(),
+            "23:10:00.00    GAME -- Ending mission - 'Game over'".into(),
+            "23:20:00.00    GAME -- *** Beginning mission 2p_calderisdunes (2 Humans, 0 Computers) ***".into(),
+            "00:05:00.00    GAME -- Ending mission - 'Abort'".into(),
+        ];
+        game_list.parse().unwrap();
+
+        assert_eq!(game_list.games[0].ended_at, "2022-11-17T00:10:00+01:00");
+        assert_eq!(game_list.games[1].started_at, "2022-11-17T00:20:00+01:00");
+        assert_eq!(game_list.games[1].ended_at, "2022-11-17T01:05:00+01:00");
+        assert_eq!(
+            game_list.games[1].real_duration(),
+            Some(Duration::minutes(45))
+        );
+    }
+
+    #[test]
+    fn session_info_is_read_from_header() {
+        let logfilepath = Path::new("warnings.txt");
+        let mut game_list = LogfileGameList::new();
+        game_list.read_logfile(logfilepath).unwrap();
+        game_list.parse().unwrap();
+
+        let session = &game_list.session;
+        assert_eq!(
+            session.executable,
+            "E:\\Games\\SteamLibrary\\steamapps\\common\\Dawn of War II - Retribution\\DOW2.exe"
+        );
+        assert_eq!(session.started_at, "2022-11-16T11:25:52+01:00");
+        assert_eq!(session.os, "NT 6.2");
+        assert_eq!(session.physical_memory_mb, 32685);
+        assert_eq!(
+            session.run_options,
+            vec![
+                "-nomovies",
+                "-modname",
+                "Elite",
+                "-refresh",
+                "58",
+                "-nomovies"
+            ]
+        );
+        assert_eq!(session.mod_name, "elite");
+        assert_eq!(session.mod_version, "2.9.5");
+        assert_eq!(session.cpu_cores, 16);
+        assert_eq!(session.hardware_threads, 16);
+
+        assert!(game_list.games.iter().all(|game| &game.session == session));
+    }
+
+    #[test]
+    fn load_checksums_and_times_are_read() {
+        let logfilepath = Path::new("warnings.txt");
+        let mut game_list = LogfileGameList::new();
+        game_list.read_logfile(logfilepath).unwrap();
+        game_list.parse().unwrap();
+
+        let game = &game_list.games[0];
+        let player = game
+            .players
+            .iter()
+            .find(|player| player.steam_id == 76561198099396483)
+            .unwrap();
+        assert_eq!(player.load_checksum, Some(4244482950));
+        assert_eq!(player.load_time_ms, Some(11810));
+        assert!(!game.checksum_mismatch());
+    }
+
+    #[test]
+    fn differing_load_checksums_are_flagged() {
+        let mut game_list = LogfileGameList::new();
+        game_list.logfile_content = vec![
+            "10:27:44.27    Match Started - [00000000:009b51a6 /steam/76561197978951214], slot =  0, ranking =   -1".into(),
+            "10:27:44.27    Match Started - [00000000:009b463d /steam/76561198099396483], slot =  4, ranking =   -1".into(),
+            "10:27:45.84    GAME -- *** Beginning mission 2p_calderisdunes (2 Humans, 0 Computers) ***".into(),
+            "10:27:45.97    LoadArbitrator::UpdateLoadProgress - player \"[sid 00000000:00000002, pid 0:10176061, /steam/76561198099396483]\" has started loading with checksum [0].".into(),
+            "10:27:46.06    LoadArbitrator::UpdateLoadProgress - player \"[sid 00000000:00000001, pid 0:10178982, /steam/76561197978951214]\" has started loading with checksum [0].".into(),
+            "10:27:57.78    LoadArbitrator::UpdateLoadProgress - info, player \"[sid 00000000:00000002, pid 0:10176061, /steam/76561198099396483]\" finished loading with checksum [4244482950].".into(),
+            "10:27:58.18    LoadArbitrator::UpdateLoadProgress - info, player \"[sid 00000000:00000001, pid 0:10178982, /steam/76561197978951214]\" finished loading with checksum [1764948934].".into(),
+            "10:27:59.78    LoadArbitrator::UpdateLoadProgress - info, player \"[sid 00000000:00000002, pid 0:10176061, /steam/76561198099396483]\" finished loading with checksum [4244482950].".into(),
+            "10:39:01.32    MOD -- Game Over at frame 6599".into(),
+            "10:39:01.38    PlayerInfo - SimID:1001, raceID:4, teamID:0, uid:0:10178982, result:5:PS_WON".into(),
+            "10:39:01.38    PlayerInfo - SimID:1000, raceID:3, teamID:1, uid:0:10176061, result:7:PS_CONCEDED".into(),
+            "10:39:07.91    GAME -- Ending mission - 'Game over'".into(),
+        ];
+        game_list.parse().unwrap();
+
+        let game = &game_list.games[0];
+        assert_eq!(game.players[0].load_checksum, Some(1764948934));
+        assert_eq!(game.players[0].load_time_ms, Some(12120));
+        // Repeated while waiting for the other player
+        assert_eq!(game.players[1].load_time_ms, Some(11810));
+        assert!(game.checksum_mismatch());
+    }
+
+    #[test]
+    fn computer_opponents_are_told_apart_from_dropped_humans() {
+        let logfilepath = Path::new("warnings.txt");
+        let mut game_list = LogfileGameList::new();
+        game_list.read_logfile(logfilepath).unwrap();
+        game_list.parse().unwrap();
+
+        // A human that dropped and was taken over by the AI
+        let game = game_list
+            .games
+            .iter()
+            .find(|game| {
+                game.players
+                    .iter()
+                    .any(|player| player.status == LogfilePlayerStatus::Dropped)
+            })
+            .unwrap();
+        assert_eq!((game.humans, game.computers), (6, 0));
+        assert!(!game.has_computer_players());
+
+        let mut game_list = LogfileGameList::new();
+        game_list.logfile_content = vec![
+            "10:24:27.10    Match Started - [00000000:009b463d /steam/76561198099396483], slot =  0, ranking =   -1".into(),
+            "10:24:29.01    GAME -- *** Beginning mission 2p_calderisrefinery (1 Humans, 1 Computers) ***".into(),
+            "10:35:01.38    PlayerInfo - SimID:1000, raceID:3, teamID:0, uid:0:10176061, result:5:PS_WON".into(),
+            "10:35:01.38    ReportMatchStatsForPVP - SimID:1001, raceID:4, teamID:1, uid:[00000000:00000001], AI player, ignoring".into(),
+            "10:35:06.19    GAME -- Ending mission - 'Game over'".into(),
+        ];
+        game_list.parse().unwrap();
+
+        let game = &game_list.games[0];
+        assert_eq!((game.humans, game.computers), (1, 1));
+        assert!(game.has_computer_players());
+        assert!(!game.players[0].computer);
+        assert!(game.players[1].computer);
+        assert_eq!(game.players[1].status, LogfilePlayerStatus::Unknown);
+    }
+
+    #[test]
+    fn leaver_timeline_identifies_first_leaver() {
+        let logfilepath = Path::new("warnings.txt");
+        let mut game_list = LogfileGameList::new();
+        game_list.read_logfile(logfilepath).unwrap();
+        game_list.parse().unwrap();
+
+        let game = game_list
+            .games
+            .iter()
+            .find(|game| game.first_leaver().is_some())
+            .unwrap();
+        let kinds = game
+            .leaver_timeline
+            .iter()
+            .map(|event| event.kind)
+            .collect::<Vec<_>>();
+        // The rest of the team conceded after the drop
+        assert_eq!(
+            kinds,
+            vec![
+                LeaveKind::Dropped,
+                LeaveKind::Disconnected,
+                LeaveKind::Conceded,
+                LeaveKind::Conceded
+            ]
+        );
+
+        let first_leaver = game.first_leaver().unwrap();
+        assert_eq!(first_leaver.sim_id, Some(1000));
+        assert_eq!(first_leaver.station, Some(2));
+        assert_eq!(first_leaver.frame, Some(8390));
+        assert_eq!(first_leaver.game_time_secs, Some(839));
+        assert_eq!(first_leaver.at, "2022-11-16T17:21:56+01:00");
+        assert_eq!(game.leaver_timeline[1].sim_id, Some(1000));
+        assert_eq!(game.leaver_timeline[1].reason_code, Some(2));
+
+        let mut game_list = LogfileGameList::new();
+        game_list.logfile_content = vec![
+            "11:12:03.77    GAME -- *** Beginning mission 6p_estia (6 Humans, 0 Computers) ***".into(),
+            "11:13:16.75    GameObj::OnRemoteStationDisconnected - info, remote station [3] was disconnected, disconnect reason code given [2], AI simulated players will migrate to [1].".into(),
+            "11:13:31.84    GameObj::SchedulePeerForDestruction - info, frame [5], peer for station [3] scheduled for destruction on frame 1.".into(),
+            "11:13:31.84    GAME -- Frame 5 - SchedulePeerForDestruction - peer 1003 scheduledfor destruction".into(),
+            "11:15:02.10    GameObj::OnRemoteStationDisconnected - info, remote station [4] was disconnected, disconnect reason code given [0], AI simulated players will migrate to [1].".into(),
+            "11:20:00.00    PlayerInfo - SimID:1001, raceID:4, teamID:1, uid:0:10176061, result:6:PS_OUTOFSYNC".into(),
+            "11:20:05.22    GAME -- Ending mission - 'Game over'".into(),
+        ];
+        game_list.parse().unwrap();
+
+        let game = &game_list.games[0];
+        let first_leaver = game.first_leaver().unwrap();
+        assert_eq!(first_leaver.kind, LeaveKind::Disconnected);
+        assert_eq!(first_leaver.sim_id, Some(1003));
+        assert_eq!(game.leaver_timeline[2].kind, LeaveKind::Disconnected);
+        assert_eq!(game.leaver_timeline[2].reason_code, Some(0));
+        assert_eq!(game.leaver_timeline[3].kind, LeaveKind::OutOfSync);
+        assert_eq!(game.leaver_timeline[3].sim_id, Some(1001));
+        assert_eq!(game.leaver_timeline[3].frame, None);
+    }
+
+    #[test]
+    fn every_game_keeps_its_logged_in_profile() {
+        let mut game_list = LogfileGameList::new();
+        game_list.logfile_content = vec![
+            "10:26:06.72    Found profile: /steam/76561198099396483".into(),
+            "10:27:45.84    GAME -- *** Beginning mission 2p_calderisdunes (2 Humans, 0 Computers) ***".into(),
+            "10:39:07.91    GAME -- Ending mission - 'Game over'".into(),
+            "10:45:12.03    Found profile: /steam/76561197978951214".into(),
+            "10:47:45.84    GAME -- *** Beginning mission 2p_calderisdunes (2 Humans, 0 Computers) ***".into(),
+            "10:59:07.91    GAME -- Ending mission - 'Game over'".into(),
+        ];
+        game_list.parse().unwrap();
+
+        assert_eq!(game_list.games[0].steam_id, 76561198099396483);
+        assert_eq!(game_list.games[1].steam_id, 76561197978951214);
+        assert_eq!(
+            game_list.profiles,
+            vec![76561198099396483, 76561197978951214]
+        );
     }
 }
@@ -0,0 +1,181 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{filter::LevelFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
+
+use super::{
+    error::{ParserAppError, ParserAppResult},
+    settings::Settings,
+};
+
+const LOG_DIRECTORY: &str = "logs";
+const LOG_FILE_PREFIX: &str = "replay-parser";
+const LOG_FILE_SUFFIX: &str = "log";
+const DEFAULT_MAX_LOG_FILES: usize = 7;
+const DEFAULT_RECENT_LINES: usize = 500;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Trace => LevelFilter::TRACE,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Error => LevelFilter::ERROR,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LoggingSettings {
+    pub level: LogLevel,
+    /// Number of daily log files to keep
+    pub max_files: usize,
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::default(),
+            max_files: DEFAULT_MAX_LOG_FILES,
+        }
+    }
+}
+
+/// Keeps the background writer of the log file alive for as long as the app is running
+pub struct LogGuard(#[allow(dead_code)] WorkerGuard);
+
+pub fn get_log_dir(handle: &AppHandle) -> ParserAppResult<PathBuf> {
+    Ok(handle.path().app_data_dir()?.join(LOG_DIRECTORY))
+}
+
+/// Logs to stdout and to a daily rotated file in the app data dir. Release builds on Windows have
+/// no console, the file is the only place their logs end up.
+pub fn setup_logging(handle: &AppHandle) -> ParserAppResult<()> {
+    // Logging is not up yet, so a broken settings file can only be reported after the fact
+    let (settings, settings_error) = match Settings::load(handle) {
+        Ok(settings) => (settings.logging, None),
+        Err(e) => (LoggingSettings::default(), Some(e)),
+    };
+
+    let log_dir = get_log_dir(handle)?;
+    fs::create_dir_all(&log_dir)?;
+
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(settings.max_files.max(1))
+        .build(&log_dir)
+        .map_err(|e| ParserAppError::GenericError(e.to_string()))?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    tracing_subscriber::registry()
+        .with(LevelFilter::from(settings.level))
+        .with(fmt::layer())
+        .with(fmt::layer().with_writer(writer).with_ansi(false))
+        .init();
+
+    handle.manage(LogGuard(guard));
+
+    if let Some(e) = settings_error {
+        tracing::error!("Could not load logging settings, using defaults: {e}");
+    }
+    tracing::info!("Logging to {log_dir:?}");
+
+    Ok(())
+}
+
+/// Fallback for when `setup_logging` fails, so that there is at least a console log
+pub fn setup_stdout_logging() {
+    // Fails only if a subscriber is already set, which then keeps logging
+    let _ = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .try_init();
+}
+
+/// The last `limit` lines across all log files, oldest first
+pub fn recent_log_lines(log_dir: &Path, limit: Option<usize>) -> ParserAppResult<Vec<String>> {
+    let limit = limit.unwrap_or(DEFAULT_RECENT_LINES);
+    if limit == 0 {
+        return Ok(vec![]);
+    }
+
+    let mut lines = VecDeque::with_capacity(limit);
+
+    for log_file in log_files(log_dir)? {
+        for line in BufReader::new(File::open(log_file)?).lines() {
+            if lines.len() == limit {
+                lines.pop_front();
+            }
+            lines.push_back(line?);
+        }
+    }
+
+    Ok(lines.into())
+}
+
+/// Log files oldest first. The date suffix of the rotated files sorts chronologically.
+pub fn log_files(log_dir: &Path) -> ParserAppResult<Vec<PathBuf>> {
+    if !log_dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut log_files = fs::read_dir(log_dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    log_files.retain(|path| {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(LOG_FILE_PREFIX))
+    });
+    log_files.sort();
+
+    Ok(log_files)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::test_support::TestDir;
+
+    use super::*;
+
+    #[test]
+    fn reads_recent_lines_across_files() {
+        let test_dir = TestDir::new("logging");
+        let log_dir = test_dir.path();
+
+        fs::write(log_dir.join("replay-parser.2022-11-15.log"), "1\n2\n3\n").unwrap();
+        fs::write(log_dir.join("replay-parser.2022-11-16.log"), "4\n5\n").unwrap();
+        fs::write(log_dir.join("unrelated.txt"), "6\n").unwrap();
+
+        assert_eq!(
+            recent_log_lines(log_dir, Some(3)).unwrap(),
+            vec!["3", "4", "5"]
+        );
+        assert_eq!(recent_log_lines(log_dir, None).unwrap().len(), 5);
+    }
+}
@@ -0,0 +1,109 @@
+//! A minimal stand-in for the ESL report endpoint so that the reporting pipeline can be tested
+//! offline. Every received JSON body is recorded and answered with the configured response.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+
+pub enum MockResponse {
+    Accept,
+    Reject(String),
+    Timeout(Duration),
+    InternalServerError,
+}
+
+pub struct MockEslServer {
+    address: SocketAddr,
+    received: Arc<Mutex<Vec<Value>>>,
+}
+
+impl MockEslServer {
+    pub fn start(response: MockResponse) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let received = Arc::new(Mutex::new(vec![]));
+
+        let reports = received.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Err(e) = handle_connection(stream, &response, &reports) {
+                    tracing::error!("Mock ESL server could not handle request: {e}");
+                }
+            }
+        });
+
+        Self { address, received }
+    }
+
+    pub fn endpoint(&self) -> String {
+        self.url("/esl/esl-report.php")
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.address)
+    }
+
+    pub fn received_reports(&self) -> Vec<Value> {
+        self.received.lock().unwrap().clone()
+    }
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    response: &MockResponse,
+    received: &Mutex<Vec<Value>>,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut content_length = 0;
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or_default();
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    if let Ok(report) = serde_json::from_slice(&body) {
+        received.lock().unwrap().push(report);
+    }
+
+    let (status, body) = match response {
+        MockResponse::Accept => ("200 OK", json!({ "response": "ok" }).to_string()),
+        MockResponse::Reject(reason) => ("200 OK", json!({ "error": reason }).to_string()),
+        MockResponse::InternalServerError => (
+            "500 Internal Server Error",
+            "Internal Server Error".to_string(),
+        ),
+        MockResponse::Timeout(delay) => {
+            thread::sleep(*delay);
+            return Ok(());
+        }
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
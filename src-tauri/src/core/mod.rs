@@ -3,22 +3,54 @@ use std::{
     time::Duration,
 };
 
-use notify::RecursiveMode;
-use notify_debouncer_mini::new_debouncer;
+use notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
 use parser_lib::replay::ReplayInfo;
-use tauri::{AppHandle, Manager};
+use serde_json::json;
+use tauri::{AppHandle, Listener, Manager, WebviewWindow};
 
 use self::{
-    error::{ParserAppError, ParserAppResult},
+    error::{ParserAppError, ParserAppResult, ResultExt},
     game::ExtendedGameInformation,
+    history::HistoryDatabase,
     logfile::{LogfileGameInfo, LogfileGameList},
+    settings::{Settings, SETTINGS_CHANGED_EVENT},
+    transport::HttpReportTransport,
 };
 
+mod annotations;
+mod archive;
+mod chat_search;
+pub mod commands;
+mod diagnostics;
+mod discord;
+mod dry_run;
 pub mod error;
 pub mod game;
+mod head_to_head;
+pub mod history;
+mod league;
 mod logfile;
+pub mod logging;
+#[cfg(test)]
+mod mock_esl_server;
+mod playback;
 pub mod player_info;
+mod privacy;
+mod quarantine;
+mod rating;
 mod replay_reporter_dto;
+pub mod retention;
+mod settings;
+mod stats;
+#[cfg(test)]
+mod test_support;
+mod transport;
+
+const STAGE_PARSE_LOGFILE: &str = "parse_logfile";
+const STAGE_PARSE_REPLAY: &str = "parse_replay";
+/// How far apart the end of a game in the logfile and the write of `temp.rec` may be
+const REPLAY_MATCH_TOLERANCE_MINUTES: i64 = 10;
 
 pub struct InputFiles {
     replay_file_path: PathBuf,
@@ -26,9 +58,7 @@ pub struct InputFiles {
 }
 
 pub fn parse_logfile(logfile_path: &Path) -> error::ParserAppResult<LogfileGameInfo> {
-    let mut game_list = LogfileGameList::new();
-    game_list.read_logfile(logfile_path)?;
-    game_list.parse()?;
+    let game_list = read_game_list(logfile_path)?;
 
     let Some(last_game) = game_list.games.last() else {
         return Err(ParserAppError::ParserLibError(
@@ -39,27 +69,106 @@ pub fn parse_logfile(logfile_path: &Path) -> error::ParserAppResult<LogfileGameI
     Ok(last_game.to_owned())
 }
 
+/// The game of the logfile that `temp.rec` was recorded in. The game writes the replay when
+/// leaving the mission, so that is the game that ended closest to the replay's modification time.
+/// Falls back to the last game when the logfile carries no usable times.
+pub fn parse_logfile_for_replay(
+    logfile_path: &Path,
+    replay_file_path: &Path,
+) -> error::ParserAppResult<LogfileGameInfo> {
+    let game_list = read_game_list(logfile_path)?;
+
+    let replay_written_at = std::fs::metadata(replay_file_path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .map(chrono::DateTime::<chrono::Utc>::from);
+    let matching_game = replay_written_at.and_then(|replay_written_at| {
+        game_list.game_ended_near(
+            replay_written_at,
+            chrono::Duration::minutes(REPLAY_MATCH_TOLERANCE_MINUTES),
+        )
+    });
+
+    let game = match matching_game {
+        Some(game) => game,
+        None => {
+            tracing::debug!("No game ended near {replay_written_at:?}, using the last game");
+            let Some(last_game) = game_list.games.last() else {
+                return Err(ParserAppError::ParserLibError(
+                    "Could not get last game from list of games in logfile".into(),
+                ));
+            };
+            last_game
+        }
+    };
+
+    // Lenient parsing keeps the other games of the session. A game that lost players or results
+    // is given up on, other skipped lines end up as warnings of the report.
+    if let Some(diagnostic) = game
+        .diagnostics
+        .iter()
+        .find(|diagnostic| diagnostic.affects_report())
+    {
+        return Err(ParserAppError::LogfileParseError(format!(
+            "Players or results of game {} could not be parsed, line {}: {}",
+            game.id, diagnostic.line_no, diagnostic.reason
+        )));
+    }
+
+    Ok(game.to_owned())
+}
+
+fn read_game_list(logfile_path: &Path) -> error::ParserAppResult<LogfileGameList> {
+    let mut game_list = LogfileGameList::new();
+    game_list.read_logfile(logfile_path)?;
+    game_list.parse_lenient()?;
+
+    if !game_list.diagnostics.is_empty() {
+        tracing::warn!(
+            "Skipped {} unexpected lines in {logfile_path:?}",
+            game_list.diagnostics.len()
+        );
+    }
+
+    Ok(game_list)
+}
+
 fn parse_replay_file(replay_file_path: String) -> ParserAppResult<ReplayInfo> {
     let parsed_replay = parser_lib::parse_raw(replay_file_path)?;
     Ok(parsed_replay)
 }
 
-pub fn get_input_files(handle: &AppHandle) -> ParserAppResult<InputFiles> {
-    let logfile_path = handle
+/// Folder the game writes `temp.rec` to and where copies of every reported replay are kept
+pub fn get_playback_dir(handle: &AppHandle, settings: &Settings) -> ParserAppResult<PathBuf> {
+    if let Some(playback_dir) = &settings.paths.playback_dir {
+        return Ok(playback_dir.clone());
+    }
+
+    Ok(handle
         .path()
         .document_dir()?
         .join("My Games")
         .join("Dawn of War II - Retribution")
-        .join("Logfiles")
-        .join("warnings.txt");
+        .join("Playback"))
+}
 
-    let playback_path = handle
+pub fn get_logfile_path(handle: &AppHandle, settings: &Settings) -> ParserAppResult<PathBuf> {
+    if let Some(logfile_path) = &settings.paths.logfile {
+        return Ok(logfile_path.clone());
+    }
+
+    Ok(handle
         .path()
         .document_dir()?
         .join("My Games")
         .join("Dawn of War II - Retribution")
-        .join("Playback")
-        .join("temp.rec");
+        .join("Logfiles")
+        .join("warnings.txt"))
+}
+
+pub fn get_input_files(handle: &AppHandle, settings: &Settings) -> ParserAppResult<InputFiles> {
+    let logfile_path = get_logfile_path(handle, settings)?;
+    let playback_path = get_playback_dir(handle, settings)?.join("temp.rec");
 
     tracing::debug!("Replay path: {playback_path:?}");
     tracing::debug!("Log path: {logfile_path:?}");
@@ -78,41 +187,251 @@ pub fn get_input_files(handle: &AppHandle) -> ParserAppResult<InputFiles> {
     })
 }
 
-pub fn handle_new_game_event(handle: &AppHandle) -> ParserAppResult<()> {
-    let Some(main_window_handle) = handle.get_webview_window("main") else {
-        return Err(ParserAppError::GenericError(
-            "Could not acquire main window handle. This is unrecoverable".into(),
-        ));
-    };
+/// Everything a game goes through once its inputs are on disk, shared by the file watcher and
+/// the reprocessing of quarantined games
+pub struct GamePipeline {
+    settings: Settings,
+    transport: HttpReportTransport,
+    dry_run_dir: PathBuf,
+    playback_dir: PathBuf,
+    main_window_handle: WebviewWindow,
+}
+
+impl GamePipeline {
+    pub fn new(handle: &AppHandle) -> ParserAppResult<Self> {
+        let Some(main_window_handle) = handle.get_webview_window("main") else {
+            return Err(ParserAppError::GenericError(
+                "Could not acquire main window handle. This is unrecoverable".into(),
+            ));
+        };
+
+        let settings = Settings::load(handle)?;
+
+        Ok(Self {
+            transport: HttpReportTransport::new(
+                &settings.report.endpoint,
+                settings.report.timeout(),
+            )?,
+            dry_run_dir: settings.dry_run.output_dir(handle)?,
+            playback_dir: get_playback_dir(handle, &settings)?,
+            settings,
+            main_window_handle,
+        })
+    }
+
+    pub fn process(
+        &self,
+        handle: &AppHandle,
+        logfile_path: &Path,
+        replay_file_path: &PathBuf,
+    ) -> ParserAppResult<ExtendedGameInformation> {
+        let settings = &self.settings;
+
+        let logfile_game_info = parse_logfile_for_replay(logfile_path, replay_file_path)
+            .with_path(logfile_path)
+            .in_stage(STAGE_PARSE_LOGFILE)?;
+        let replay_file_info = parse_replay_file(replay_file_path.to_str().unwrap().to_string())
+            .with_path(replay_file_path)
+            .in_stage(STAGE_PARSE_REPLAY)?;
+
+        let mut replay_info = ExtendedGameInformation::new();
+        replay_info
+            .from(replay_file_info, &logfile_game_info)
+            .apply_league_rules(&settings.league)
+            .check_mod_version(settings.report.expected_mod_version.as_deref());
+
+        let reported = self.report(&mut replay_info, replay_file_path);
+        if let Err(e) = &reported {
+            // Keep the game in the history as not sent, so that it can be reported again later
+            if !replay_info.sent {
+                replay_info.status =
+                    json!({ "error": e.to_string(), "code": e.code() }).to_string();
+                replay_info.replay = None;
+            }
+        }
+
+        match handle.try_state::<HistoryDatabase>() {
+            Some(history) => {
+                if let Err(e) = history.save_game(&replay_info) {
+                    tracing::error!("Could not save match {} to history: {e}", replay_info.id);
+                }
+            }
+            None => tracing::error!("History database is not available"),
+        }
+
+        reported.map(|_| replay_info)
+    }
+
+    /// Everything after parsing: keeping a copy of the replay, reporting the game and telling the
+    /// frontend and Discord about it
+    fn report(
+        &self,
+        replay_info: &mut ExtendedGameInformation,
+        replay_file_path: &PathBuf,
+    ) -> ParserAppResult<()> {
+        let settings = &self.settings;
+        let match_id = replay_info.id;
 
+        // Games that are not reported need neither the replay copy nor its encoding
+        if settings.report.skip_computer_games && replay_info.computer_players {
+            replay_info.skip_report("game has computer opponents");
+        } else {
+            replay_info
+                .copy_replay_file(
+                    replay_file_path,
+                    &self.playback_dir,
+                    &settings.paths.replay_copy_name,
+                )
+                .in_stage("copy_replay")
+                .with_match_id(match_id)?
+                .transform_replay_to_base64(replay_file_path)
+                .in_stage("encode_replay")
+                .with_match_id(match_id)?;
+
+            if settings.dry_run.enabled {
+                replay_info
+                    .write_report_to_disk(
+                        &self.dry_run_dir,
+                        replay_file_path,
+                        &settings.paths.replay_copy_name,
+                        &settings.privacy,
+                    )
+                    .in_stage("write_report")
+                    .with_match_id(match_id)?;
+            } else {
+                replay_info
+                    .send_replay_to_server(&self.transport, &settings.privacy)
+                    .in_stage("send_report")
+                    .with_match_id(match_id)?;
+            }
+        }
+
+        replay_info
+            .notify_main_window(&self.main_window_handle)
+            .in_stage("notify_main_window")
+            .with_match_id(match_id)?;
+        // Dry runs are for trying out settings, nothing must leave the machine
+        if !settings.dry_run.enabled {
+            discord::publish_to_sinks(
+                &settings.discord_webhooks,
+                replay_info,
+                settings.report.timeout(),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether the error means the inputs themselves could not be understood, as opposed to e.g. the
+/// network being down
+fn is_parse_error(error: &ParserAppError) -> bool {
+    matches!(
+        error.context().stage,
+        Some(STAGE_PARSE_LOGFILE | STAGE_PARSE_REPLAY)
+    )
+}
+
+enum WatcherMessage {
+    Events(DebounceEventResult),
+    SettingsChanged,
+}
+
+/// Watches `temp.rec` with the settings that were current when it was started
+struct GameWatcher {
+    input_files: InputFiles,
+    pipeline: GamePipeline,
+    // Stops watching when dropped
+    _debouncer: Debouncer<RecommendedWatcher>,
+}
+
+impl GameWatcher {
+    fn start(
+        handle: &AppHandle,
+        tx: std::sync::mpsc::Sender<WatcherMessage>,
+    ) -> ParserAppResult<Self> {
+        let pipeline = GamePipeline::new(handle)?;
+        let input_files = get_input_files(handle, &pipeline.settings)?;
+
+        let mut debouncer = new_debouncer(
+            Duration::from_secs(pipeline.settings.watcher.debounce_secs),
+            None,
+            move |events| {
+                let _ = tx.send(WatcherMessage::Events(events));
+            },
+        )?;
+
+        debouncer.watcher().watch(
+            Path::new(input_files.replay_file_path.as_os_str()),
+            RecursiveMode::Recursive,
+        )?;
+
+        Ok(Self {
+            input_files,
+            pipeline,
+            _debouncer: debouncer,
+        })
+    }
+}
+
+pub fn handle_new_game_event(handle: &AppHandle) -> ParserAppResult<()> {
     let (tx, rx) = std::sync::mpsc::channel();
-    let InputFiles {
-        replay_file_path,
-        logfile_path,
-    } = get_input_files(&handle)?;
+    let quarantine_dir = quarantine::get_quarantine_dir(handle)?;
 
+    let settings_tx = tx.clone();
+    handle.listen_any(SETTINGS_CHANGED_EVENT, move |_| {
+        let _ = settings_tx.send(WatcherMessage::SettingsChanged);
+    });
 
-    let mut debouncer = new_debouncer(Duration::from_secs(5), None, tx).unwrap();
+    let mut watcher = GameWatcher::start(handle, tx.clone())?;
 
-    debouncer.watcher().watch(
-        Path::new(replay_file_path.as_os_str()),
-        RecursiveMode::Recursive,
-    )?;
+    for message in rx {
+        let events = match message {
+            WatcherMessage::Events(events) => events,
+            WatcherMessage::SettingsChanged => {
+                // Keep the running watcher if the new settings can not be applied
+                match GameWatcher::start(handle, tx.clone()) {
+                    Ok(new_watcher) => {
+                        tracing::info!("Applied new settings to the file watcher");
+                        watcher = new_watcher;
+                    }
+                    Err(e) => tracing::error!("Could not apply new settings: {e}"),
+                }
+                continue;
+            }
+        };
+
+        let InputFiles {
+            replay_file_path,
+            logfile_path,
+        } = &watcher.input_files;
 
-    for events in rx {
         for _e in events? {
             tracing::info!("Received a replay file notify event");
-            let logfile_game_info = parse_logfile(&logfile_path)?;
-            let replay_file_info =
-                parse_replay_file(replay_file_path.to_str().unwrap().to_string())?;
 
-            let mut replay_info = ExtendedGameInformation::new();
-            replay_info
-                .from(replay_file_info, &logfile_game_info)
-                .copy_replay_file(&replay_file_path)?
-                .transform_replay_to_base64(&replay_file_path)?
-                .send_replay_to_server()?
-                .notify_main_window(&main_window_handle)?;
+            match watcher
+                .pipeline
+                .process(handle, logfile_path, replay_file_path)
+            {
+                Ok(_) => (),
+                // The game will overwrite the inputs with the next game, keep them for later
+                Err(e) if is_parse_error(&e) => {
+                    // Losing the inputs is no reason to stop watching for the next game
+                    if let Err(quarantine_error) = quarantine::quarantine(
+                        &quarantine_dir,
+                        logfile_path,
+                        replay_file_path,
+                        &handle.package_info().version.to_string(),
+                        &e,
+                    ) {
+                        tracing::error!(
+                            "Could not quarantine game inputs after {e}: {quarantine_error}"
+                        );
+                    }
+                }
+                // The game is in the history as not sent, the next one may well work out
+                Err(e) => tracing::error!("Could not report the game: {e}"),
+            }
         }
     }
 
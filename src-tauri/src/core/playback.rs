@@ -0,0 +1,49 @@
+/// Default of `PathSettings::replay_copy_name`
+pub const DEFAULT_REPLAY_COPY_NAME: &str = "{id}_{map}.rec";
+
+/// Name of the copy of a replay that is kept next to `temp.rec`, since the game overwrites
+/// `temp.rec` with the next game. `{id}` and `{map}` in the template are replaced by the relic
+/// match id and the map name.
+pub fn replay_copy_name(template: &str, match_id: usize, map_name: &str) -> String {
+    template
+        .replace("{id}", &match_id.to_string())
+        .replace("{map}", map_name)
+}
+
+/// Problems with a copy name template, all copies must get distinct names inside the playback
+/// folder
+pub fn template_problems(template: &str) -> Vec<String> {
+    let mut problems = vec![];
+
+    if !template.contains("{id}") {
+        problems.push("must contain {id}".into());
+    }
+    if template.contains(['/', '\\']) {
+        problems.push("must not contain path separators".into());
+    }
+    if !template.ends_with(".rec") {
+        problems.push("must end with .rec".into());
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_copies_after_the_template() {
+        assert_eq!(
+            replay_copy_name(DEFAULT_REPLAY_COPY_NAME, 1234, "6p_estia"),
+            "1234_6p_estia.rec"
+        );
+        assert_eq!(
+            replay_copy_name("{map} - {id}.rec", 1234, "6p_estia"),
+            "6p_estia - 1234.rec"
+        );
+
+        assert!(template_problems(DEFAULT_REPLAY_COPY_NAME).is_empty());
+        assert_eq!(template_problems("replays/{map}.rec").len(), 2);
+    }
+}
@@ -14,6 +14,24 @@ lazy_static! {
         Regex::new(r"SimID:(\d+), raceID:(\d+), teamID:(\d+), uid:\[\d+:(.+)\]").unwrap();
 }
 
+const RACE_NAMES: [&str; 6] = [
+    "Space Marines",
+    "Eldar",
+    "Orks",
+    "Tyranids",
+    "Chaos",
+    "Imperial Guard",
+];
+/// Heroes of every race in the order of the hero selection, which is what the replay stores
+const HERO_NAMES: [[&str; 3]; 6] = [
+    ["Force Commander", "Apothecary", "Techmarine"],
+    ["Farseer", "Warlock", "Warp Spider Exarch"],
+    ["Warboss", "Mekboy", "Kommando Nob"],
+    ["Hive Tyrant", "Lictor Alpha", "Ravener Alpha"],
+    ["Chaos Lord", "Sorcerer", "Plague Champion"],
+    ["Lord General", "Commissar Lord", "Inquisitor"],
+];
+
 #[derive(Clone, Default, Debug, Deserialize, PartialEq, Eq, PartialOrd, Serialize)]
 pub enum LogfilePlayerStatus {
     #[default]
@@ -34,7 +52,11 @@ pub struct LogfilePlayerInfo {
     pub relic_id: usize,
     pub steam_id: usize,
     pub slot: usize,
+    pub ranking: Option<i32>,
     pub status: LogfilePlayerStatus,
+    pub computer: bool, // AI opponent added in the lobby, not a human that dropped
+    pub load_checksum: Option<i64>, // Checksum of the game files the client reported after loading
+    pub load_time_ms: Option<u64>,
 }
 
 #[derive(Clone, Default, Debug, Deserialize, Serialize)]
@@ -58,6 +80,11 @@ pub struct ExtendedPlayerInformation {
     pub skin_path: String,
     pub skin_name: String,
     pub id: u8,
+    /// Played with the Steam profile that was logged in on this machine
+    pub local: bool,
+    pub computer: bool,
+    pub load_checksum: Option<i64>,
+    pub load_time_ms: Option<u64>,
 }
 
 impl LogfilePlayerInfo {
@@ -121,10 +148,29 @@ impl ExtendedPlayerInformation {
             skin_path: replayfile_player.skin_path.clone(),
             skin_name: replayfile_player.skin_name.clone(),
             id: replayfile_player.id,
+            local: false,
+            computer: logfile_player.computer,
+            load_checksum: logfile_player.load_checksum,
+            load_time_ms: logfile_player.load_time_ms,
         }
     }
 }
 
+pub fn race_name(race: usize) -> String {
+    RACE_NAMES
+        .get(race)
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| format!("Race {race}"))
+}
+
+pub fn hero_name(race: usize, hero: u32) -> String {
+    HERO_NAMES
+        .get(race)
+        .and_then(|heroes| heroes.get(hero as usize))
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| format!("Hero {hero}"))
+}
+
 fn convert_match_to_int(capture: Match) -> usize {
     if let Ok(int) = capture.as_str().parse::<usize>() {
         return int;
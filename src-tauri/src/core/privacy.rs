@@ -0,0 +1,113 @@
+use hmac::{Hmac, Mac};
+use parser_lib::message::Message;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+pub const REDACTED_MESSAGE_BODY: &str = "[redacted]";
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatPolicy {
+    /// Every message is reported as is
+    #[default]
+    All,
+    /// Team and private messages are withheld
+    AllChatOnly,
+    /// Every message is reported without its body
+    Redacted,
+    /// No chat is reported at all
+    Omitted,
+}
+
+/// Controls which personal data is withheld from the report sent to the ESL server
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PrivacySettings {
+    pub chat: ChatPolicy,
+    pub hash_steam_ids: bool,
+    /// Secret shared with the ESL server, which uses it to map pseudonyms back to players. Required
+    /// when `hash_steam_ids` is set.
+    pub steam_id_hash_key: String,
+    /// Leaves `leaver_timeline` and `first_leaver` out of the report
+    pub omit_leaver_timeline: bool,
+}
+
+impl PrivacySettings {
+    pub fn reports_message(&self, message: &Message) -> bool {
+        match self.chat {
+            ChatPolicy::All | ChatPolicy::Redacted => true,
+            ChatPolicy::AllChatOnly => message.receiver.eq_ignore_ascii_case("all"),
+            ChatPolicy::Omitted => false,
+        }
+    }
+
+    pub fn message_body(&self, message: &Message) -> String {
+        match self.chat {
+            ChatPolicy::Redacted => REDACTED_MESSAGE_BODY.into(),
+            _ => message.body.clone(),
+        }
+    }
+
+    pub fn steam_id(&self, steam_id: usize) -> usize {
+        match self.hash_steam_ids {
+            true => hash_steam_id(steam_id, &self.steam_id_hash_key),
+            false => steam_id,
+        }
+    }
+}
+
+/// Stable pseudonym for a steam id, keyed with `key` so nobody without the key can recompute it
+/// from a list of steam ids. The same player keeps the same pseudonym across all reports.
+///
+/// This is pseudonymization, not anonymization: anybody holding the key can link pseudonyms to
+/// players, and names and relic ids in the report identify players anyway.
+pub fn hash_steam_id(steam_id: usize, key: &str) -> usize {
+    let digest = hmac_sha256(key.as_bytes(), steam_id.to_string().as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+
+    u64::from_le_bytes(bytes) as usize
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hmac_matches_rfc_4231() {
+        let digest = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        let hex = digest
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+
+        assert_eq!(
+            hex,
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn pseudonyms_stay_stable() {
+        // First 8 bytes, little endian, of the HMAC with the key of the RFC 4231 vector. Changing
+        // it would give every player of past reports a new pseudonym.
+        assert_eq!(
+            hash_steam_id(76561198099396483, "Jefe"),
+            8651532582022568164
+        );
+    }
+
+    #[test]
+    fn pseudonyms_depend_on_the_key() {
+        assert_eq!(hash_steam_id(1, "key"), hash_steam_id(1, "key"));
+        assert_ne!(hash_steam_id(1, "key"), hash_steam_id(1, "other key"));
+        assert_ne!(hash_steam_id(1, "key"), hash_steam_id(2, "key"));
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::SecondsFormat;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+
+use super::error::{ParserAppError, ParserAppResult};
+
+const QUARANTINE_DIRECTORY: &str = "quarantine";
+const ERROR_RECORD_FILE_NAME: &str = "error.json";
+pub const QUARANTINED_LOGFILE_NAME: &str = "warnings.txt";
+pub const QUARANTINED_REPLAY_NAME: &str = "temp.rec";
+
+/// A game whose inputs could not be parsed, kept so a later parser version can rescue it
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct QuarantineEntry {
+    /// Name of the entry's folder in the quarantine directory
+    pub id: String,
+    pub quarantined_at: String,
+    /// Version of the app that failed to parse the game
+    pub app_version: String,
+    /// Serialized `ParserAppError` of the most recent attempt
+    pub error: Value,
+    pub attempts: usize,
+    pub last_attempt_at: String,
+}
+
+pub fn get_quarantine_dir(handle: &AppHandle) -> ParserAppResult<PathBuf> {
+    Ok(handle.path().app_data_dir()?.join(QUARANTINE_DIRECTORY))
+}
+
+/// Copies the inputs of a failed game into a new entry of `quarantine_dir`
+pub fn quarantine(
+    quarantine_dir: &Path,
+    logfile_path: &Path,
+    replay_file_path: &Path,
+    app_version: &str,
+    error: &ParserAppError,
+) -> ParserAppResult<QuarantineEntry> {
+    let now = chrono::Utc::now();
+    let mut id = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let mut suffix = 1;
+    while quarantine_dir.join(&id).exists() {
+        suffix += 1;
+        id = format!("{}-{suffix}", now.format("%Y%m%dT%H%M%SZ"));
+    }
+
+    let entry_dir = quarantine_dir.join(&id);
+    fs::create_dir_all(&entry_dir)?;
+
+    // Either input may be what is missing, keep whatever is there
+    if logfile_path.exists() {
+        fs::copy(logfile_path, entry_dir.join(QUARANTINED_LOGFILE_NAME))?;
+    }
+    if replay_file_path.exists() {
+        fs::copy(replay_file_path, entry_dir.join(QUARANTINED_REPLAY_NAME))?;
+    }
+
+    let quarantined_at = now.to_rfc3339_opts(SecondsFormat::Secs, true);
+    let entry = QuarantineEntry {
+        id,
+        quarantined_at: quarantined_at.clone(),
+        app_version: app_version.into(),
+        error: serde_json::to_value(error)?,
+        attempts: 1,
+        last_attempt_at: quarantined_at,
+    };
+    write_entry(quarantine_dir, &entry)?;
+
+    tracing::warn!("Quarantined game inputs as {:?}: {error}", entry.id);
+    Ok(entry)
+}
+
+/// All quarantined games, oldest first
+pub fn list(quarantine_dir: &Path) -> ParserAppResult<Vec<QuarantineEntry>> {
+    if !quarantine_dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut entries = vec![];
+    for dir_entry in fs::read_dir(quarantine_dir)? {
+        let path = dir_entry?.path();
+        let record_path = path.join(ERROR_RECORD_FILE_NAME);
+        if !record_path.exists() {
+            continue;
+        }
+
+        match serde_json::from_slice::<QuarantineEntry>(&fs::read(&record_path)?) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => tracing::error!("Skipping unreadable quarantine record {record_path:?}: {e}"),
+        }
+    }
+
+    // Games quarantined within the same second only differ by the numeric suffix of their id
+    entries.sort_by_key(|entry| (entry.quarantined_at.clone(), id_suffix(&entry.id)));
+    Ok(entries)
+}
+
+pub fn load(quarantine_dir: &Path, id: &str) -> ParserAppResult<QuarantineEntry> {
+    let record_path = entry_dir(quarantine_dir, id)?.join(ERROR_RECORD_FILE_NAME);
+    if !record_path.exists() {
+        return Err(ParserAppError::NotFoundError(format!(
+            "quarantined game {id}"
+        )));
+    }
+
+    Ok(serde_json::from_slice(&fs::read(record_path)?)?)
+}
+
+/// Paths of the quarantined warnings.txt and temp.rec
+pub fn input_files(quarantine_dir: &Path, id: &str) -> ParserAppResult<(PathBuf, PathBuf)> {
+    let entry_dir = entry_dir(quarantine_dir, id)?;
+
+    Ok((
+        entry_dir.join(QUARANTINED_LOGFILE_NAME),
+        entry_dir.join(QUARANTINED_REPLAY_NAME),
+    ))
+}
+
+/// Keeps the entry around with the error of another failed reprocessing attempt
+pub fn record_failed_attempt(
+    quarantine_dir: &Path,
+    id: &str,
+    error: &ParserAppError,
+) -> ParserAppResult<QuarantineEntry> {
+    let mut entry = load(quarantine_dir, id)?;
+    entry.error = serde_json::to_value(error)?;
+    entry.attempts += 1;
+    entry.last_attempt_at = chrono::Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    write_entry(quarantine_dir, &entry)?;
+
+    Ok(entry)
+}
+
+pub fn remove(quarantine_dir: &Path, id: &str) -> ParserAppResult<()> {
+    fs::remove_dir_all(entry_dir(quarantine_dir, id)?)?;
+
+    Ok(())
+}
+
+fn write_entry(quarantine_dir: &Path, entry: &QuarantineEntry) -> ParserAppResult<()> {
+    let record_path = quarantine_dir.join(&entry.id).join(ERROR_RECORD_FILE_NAME);
+    fs::write(record_path, serde_json::to_vec_pretty(entry)?)?;
+
+    Ok(())
+}
+
+/// `2` for `20221116T172156Z-2`, `1` for the first entry of a second which has no suffix
+fn id_suffix(id: &str) -> usize {
+    id.rsplit_once('-')
+        .and_then(|(_, suffix)| suffix.parse().ok())
+        .unwrap_or(1)
+}
+
+/// Ids come from the frontend, make sure they can not point outside of the quarantine directory
+fn entry_dir(quarantine_dir: &Path, id: &str) -> ParserAppResult<PathBuf> {
+    let is_plain_name = Path::new(id).file_name().is_some_and(|name| name == id);
+    if !is_plain_name {
+        return Err(ParserAppError::NotFoundError(format!(
+            "quarantined game {id}"
+        )));
+    }
+
+    Ok(quarantine_dir.join(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{parse_logfile, test_support::TestDir};
+
+    use super::*;
+
+    #[test]
+    fn quarantines_and_lists_failed_games() {
+        let test_dir = TestDir::new("quarantine");
+        let quarantine_dir = test_dir.join("quarantine");
+
+        let logfile_path = Path::new("warnings.txt");
+        let error = ParserAppError::LogfileParseError("Found more than 1 game to parse".into());
+
+        let first = quarantine(
+            &quarantine_dir,
+            logfile_path,
+            Path::new("3v3.rec"),
+            "1.0.6",
+            &error,
+        )
+        .unwrap();
+        let second = quarantine(
+            &quarantine_dir,
+            logfile_path,
+            Path::new("missing.rec"),
+            "1.0.6",
+            &error,
+        )
+        .unwrap();
+        assert_ne!(first.id, second.id);
+
+        let entries = list(&quarantine_dir).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].error["code"], "logfile_parse");
+
+        // A newer parser handles the quarantined logfile
+        let (quarantined_logfile, quarantined_replay) =
+            input_files(&quarantine_dir, &first.id).unwrap();
+        assert!(quarantined_replay.exists());
+        assert!(parse_logfile(&quarantined_logfile).is_ok());
+
+        let retried = record_failed_attempt(&quarantine_dir, &second.id, &error).unwrap();
+        assert_eq!(retried.attempts, 2);
+
+        remove(&quarantine_dir, &first.id).unwrap();
+        assert_eq!(list(&quarantine_dir).unwrap().len(), 1);
+        assert!(load(&quarantine_dir, "../quarantine").is_err());
+    }
+
+    #[test]
+    fn lists_games_of_the_same_second_in_order() {
+        let test_dir = TestDir::new("quarantine_order");
+        let quarantine_dir = test_dir.join("quarantine");
+
+        for id in [
+            "20221116T172156Z-10",
+            "20221116T172156Z",
+            "20221116T172156Z-2",
+        ] {
+            fs::create_dir_all(quarantine_dir.join(id)).unwrap();
+            let entry = QuarantineEntry {
+                id: id.into(),
+                quarantined_at: "2022-11-16T17:21:56Z".into(),
+                ..Default::default()
+            };
+            write_entry(&quarantine_dir, &entry).unwrap();
+        }
+
+        let ids = list(&quarantine_dir)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec![
+                "20221116T172156Z",
+                "20221116T172156Z-2",
+                "20221116T172156Z-10"
+            ]
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use super::{history::MatchRecord, player_info::LogfilePlayerStatus};
+
+/// What to do with games in which a player dropped or went out of sync
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DroppedGameRule {
+    #[default]
+    Skip, // The game does not affect anybody's rating
+    Rate, // The game is rated like any other game
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RatingSettings {
+    pub initial_rating: f64,
+    pub k_factor: f64,
+    pub dropped_games: DroppedGameRule,
+}
+
+impl Default for RatingSettings {
+    fn default() -> Self {
+        Self {
+            initial_rating: 1500.0,
+            k_factor: 32.0,
+            dropped_games: DroppedGameRule::Skip,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PlayerRating {
+    pub steam_id: usize,
+    pub name: String,
+    pub rating: f64,
+    pub games: usize,
+    pub history: Vec<RatingChange>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RatingChange {
+    pub match_id: i64,
+    pub played_at: String,
+    pub rating_before: f64,
+    pub rating_after: f64,
+}
+
+/// Elo rating over the local match history. In team games every player is rated against the
+/// average rating of the opposing team, based on the average rating of their own team.
+pub struct RatingEngine {
+    settings: RatingSettings,
+    ratings: HashMap<usize, PlayerRating>,
+}
+
+impl RatingEngine {
+    pub fn new(settings: RatingSettings) -> Self {
+        Self {
+            settings,
+            ratings: HashMap::new(),
+        }
+    }
+
+    /// Rates all matches in the given (chronological) order and returns the resulting ratings,
+    /// best player first
+    pub fn replay(settings: RatingSettings, matches: &[MatchRecord]) -> Vec<PlayerRating> {
+        let mut engine = Self::new(settings);
+        for record in matches.iter() {
+            engine.rate(record);
+        }
+
+        engine.ratings()
+    }
+
+    pub fn rate(&mut self, record: &MatchRecord) {
+        if !self.is_rated(record) {
+            return;
+        }
+
+        let Some(winning_team) = record
+            .players
+            .iter()
+            .find(|player| player.status == LogfilePlayerStatus::Won)
+            .map(|player| player.team)
+        else {
+            return;
+        };
+
+        let mut teams: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+        for player in record.players.iter().filter(|player| player.steam_id != 0) {
+            teams.entry(player.team).or_default().push(player.steam_id);
+        }
+
+        // Only games between exactly two teams can be rated
+        if teams.len() != 2 {
+            return;
+        }
+
+        for player in record.players.iter().filter(|player| player.steam_id != 0) {
+            let initial_rating = self.settings.initial_rating;
+            let rating = self
+                .ratings
+                .entry(player.steam_id)
+                .or_insert_with(|| PlayerRating {
+                    steam_id: player.steam_id,
+                    name: player.name.clone(),
+                    rating: initial_rating,
+                    games: 0,
+                    history: vec![],
+                });
+            rating.name = player.name.clone();
+        }
+
+        let averages = teams
+            .iter()
+            .map(|(team, steam_ids)| (*team, self.average_rating(steam_ids)))
+            .collect::<BTreeMap<_, _>>();
+
+        for (team, steam_ids) in teams.iter() {
+            let own_average = averages[team];
+            let opposing_average = averages
+                .iter()
+                .find(|(other, _)| *other != team)
+                .map(|(_, average)| *average)
+                .unwrap_or(own_average);
+
+            let expected = 1.0 / (1.0 + 10f64.powf((opposing_average - own_average) / 400.0));
+            let score = match *team == winning_team {
+                true => 1.0,
+                false => 0.0,
+            };
+            let delta = self.settings.k_factor * (score - expected);
+
+            for steam_id in steam_ids.iter() {
+                if let Some(rating) = self.ratings.get_mut(steam_id) {
+                    let rating_before = rating.rating;
+                    rating.rating += delta;
+                    rating.games += 1;
+                    rating.history.push(RatingChange {
+                        match_id: record.id,
+                        played_at: record.played_at.clone(),
+                        rating_before,
+                        rating_after: rating.rating,
+                    });
+                }
+            }
+        }
+    }
+
+    pub fn ratings(self) -> Vec<PlayerRating> {
+        let mut ratings = self.ratings.into_values().collect::<Vec<_>>();
+        ratings.sort_by(|a, b| b.rating.total_cmp(&a.rating));
+        ratings
+    }
+
+    fn is_rated(&self, record: &MatchRecord) -> bool {
+        if record.aborted {
+            return false;
+        }
+
+        let has_dropped_player = record.players.iter().any(|player| {
+            matches!(
+                player.status,
+                LogfilePlayerStatus::Dropped | LogfilePlayerStatus::Outofsync
+            )
+        });
+
+        !(has_dropped_player && self.settings.dropped_games == DroppedGameRule::Skip)
+    }
+
+    fn average_rating(&self, steam_ids: &[usize]) -> f64 {
+        let total = steam_ids
+            .iter()
+            .filter_map(|steam_id| self.ratings.get(steam_id))
+            .map(|rating| rating.rating)
+            .sum::<f64>();
+
+        total / steam_ids.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::test_support::player;
+
+    use super::*;
+
+    fn game(id: i64, winners: [usize; 2], losers: [usize; 2]) -> MatchRecord {
+        MatchRecord {
+            id,
+            players: vec![
+                player(winners[0], 0, LogfilePlayerStatus::Won),
+                player(winners[1], 0, LogfilePlayerStatus::Won),
+                player(losers[0], 1, LogfilePlayerStatus::Conceded),
+                player(losers[1], 1, LogfilePlayerStatus::Conceded),
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn rating_of(ratings: &[PlayerRating], steam_id: usize) -> f64 {
+        ratings
+            .iter()
+            .find(|rating| rating.steam_id == steam_id)
+            .unwrap()
+            .rating
+    }
+
+    #[test]
+    fn rates_team_games() {
+        let ratings = RatingEngine::replay(
+            RatingSettings::default(),
+            &[game(1, [1, 2], [3, 4]), game(2, [1, 3], [2, 4])],
+        );
+
+        assert_eq!(rating_of(&ratings, 1), 1500.0 + 16.0 + 16.0);
+        assert_eq!(rating_of(&ratings, 4), 1500.0 - 16.0 - 16.0);
+        assert_eq!(rating_of(&ratings, 2), rating_of(&ratings, 3));
+        assert_eq!(ratings[0].steam_id, 1);
+        assert_eq!(ratings[0].history.len(), 2);
+        assert_eq!(ratings[0].history[1].match_id, 2);
+    }
+
+    #[test]
+    fn skips_aborted_and_dropped_games() {
+        let aborted = MatchRecord {
+            aborted: true,
+            ..game(1, [1, 2], [3, 4])
+        };
+        let mut dropped = game(2, [1, 2], [3, 4]);
+        dropped.players[3].status = LogfilePlayerStatus::Dropped;
+
+        let ratings = RatingEngine::replay(RatingSettings::default(), &[aborted, dropped]);
+        assert!(ratings.is_empty());
+
+        let mut dropped = game(2, [1, 2], [3, 4]);
+        dropped.players[3].status = LogfilePlayerStatus::Dropped;
+        let settings = RatingSettings {
+            dropped_games: DroppedGameRule::Rate,
+            ..Default::default()
+        };
+
+        let ratings = RatingEngine::replay(settings, &[dropped]);
+        assert_eq!(rating_of(&ratings, 1), 1516.0);
+    }
+
+    #[test]
+    fn leaves_out_players_of_unratable_games() {
+        let mut no_winner = game(1, [1, 2], [3, 4]);
+        no_winner.players[0].status = LogfilePlayerStatus::Killed;
+        no_winner.players[1].status = LogfilePlayerStatus::Killed;
+
+        let mut three_teams = game(2, [5, 6], [7, 8]);
+        three_teams.players[3].team = 2;
+
+        let ratings = RatingEngine::replay(RatingSettings::default(), &[no_winner, three_teams]);
+        assert!(ratings.is_empty());
+    }
+}
@@ -4,7 +4,9 @@ use serde::Serialize;
 
 use super::{
     game::ExtendedGameInformation,
+    logfile::LeaverEvent,
     player_info::{ExtendedPlayerInformation, LogfilePlayerStatus},
+    privacy::{ChatPolicy, PrivacySettings},
 };
 
 #[derive(Debug, Serialize)]
@@ -17,14 +19,34 @@ pub struct ReplayReportDto {
     reporter: ReplayReportReporterDto,
     replay: String,
     mod_version: usize,
+    /// Version string the client logged on startup, e.g. `2.9.5`. `mod_version` is the replay's
+    /// numeric build.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    mod_release: String,
     ranked: bool,
     league: bool,
+    checksum_mismatch: bool,
     frames: usize,
     ticks: usize,
     players: Vec<ReplayReporterPlayerDto>,
     messages: Vec<ReplayReporterMessageDto>,
     // observers: Vec<>
     winner: usize,
+    privacy: ReplayReportPrivacyDto,
+    /// Only sent for games somebody left early or conceded, and not at all with
+    /// `PrivacySettings::omit_leaver_timeline`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    leaver_timeline: Vec<LeaverEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    first_leaver: Option<LeaverEvent>,
+}
+
+/// Tells the ESL server which data was withheld by the reporting player
+#[derive(Debug, Serialize)]
+pub struct ReplayReportPrivacyDto {
+    chat: ChatPolicy,
+    steam_ids_hashed: bool,
+    leaver_timeline_omitted: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -43,6 +65,7 @@ pub struct ReplayReporterPlayerDto {
     team: usize,
     sim_id: usize,
     slot: usize,
+    computer: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -55,26 +78,27 @@ pub struct ReplayReporterMessageDto {
 }
 
 impl ReplayReporterPlayerDto {
-    pub fn from(player: &ExtendedPlayerInformation) -> Self {
+    pub fn from(player: &ExtendedPlayerInformation, privacy: &PrivacySettings) -> Self {
         Self {
             relic_id: player.relic_id as usize,
             hero: player.hero as usize,
             race: player.race,
             name: player.name.clone(),
-            steam_id: player.steam_id,
+            steam_id: privacy.steam_id(player.steam_id),
             team: player.team as usize,
             sim_id: player.sim_id,
             slot: player.slot,
+            computer: player.computer,
         }
     }
 }
 
 impl ReplayReporterMessageDto {
-    pub fn from(message: &Message) -> Self {
+    pub fn from(message: &Message, privacy: &PrivacySettings) -> Self {
         Self {
             receiver: message.receiver.clone(),
             sender: message.sender.clone(),
-            body: message.body.clone(),
+            body: privacy.message_body(message),
             tick: message.tick as usize,
             player_id: message.player_id as usize,
         }
@@ -82,7 +106,7 @@ impl ReplayReporterMessageDto {
 }
 
 impl ReplayReportDto {
-    pub fn from(replay: &ExtendedGameInformation) -> Self {
+    pub fn from(replay: &ExtendedGameInformation, privacy: &PrivacySettings) -> Self {
         let winner = replay
             .players
             .iter()
@@ -108,7 +132,7 @@ impl ReplayReportDto {
                 false
             },
             id: replay.id.to_string(),
-            map: replay.map.path.clone().replace("DATA:maps\\pvp\\", ""),
+            map: replay.map_name(),
             reporter: ReplayReportReporterDto {
                 date: chrono::Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
                 version: "1.0.6".into(),
@@ -119,21 +143,177 @@ impl ReplayReportDto {
                 "".into()
             },
             mod_version: replay.mod_version,
-            ranked: false,
-            league: false,
+            mod_release: replay.session.mod_version.clone(),
+            ranked: replay.ranked,
+            league: replay.league,
+            checksum_mismatch: replay.checksum_mismatch,
             frames: replay.frames,
             ticks: replay.ticks,
             players: replay
                 .players
                 .iter()
-                .map(ReplayReporterPlayerDto::from)
+                .map(|player| ReplayReporterPlayerDto::from(player, privacy))
                 .collect(),
             messages: replay
                 .messages
                 .iter()
-                .map(ReplayReporterMessageDto::from)
+                .filter(|message| privacy.reports_message(message))
+                .map(|message| ReplayReporterMessageDto::from(message, privacy))
                 .collect(),
             winner: winner as usize,
+            privacy: ReplayReportPrivacyDto {
+                chat: privacy.chat,
+                steam_ids_hashed: privacy.hash_steam_ids,
+                leaver_timeline_omitted: privacy.omit_leaver_timeline,
+            },
+            leaver_timeline: match privacy.omit_leaver_timeline {
+                true => vec![],
+                false => replay.leaver_timeline.clone(),
+            },
+            first_leaver: replay
+                .first_leaver
+                .clone()
+                .filter(|_| !privacy.omit_leaver_timeline),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{
+        logfile::LeaveKind,
+        privacy::{hash_steam_id, REDACTED_MESSAGE_BODY},
+    };
+
+    use super::*;
+
+    fn message(receiver: &str, body: &str) -> Message {
+        Message {
+            tick: 10,
+            sender: "Adeptus Noobus".into(),
+            receiver: receiver.into(),
+            body: body.into(),
+            player_id: 1000,
+        }
+    }
+
+    fn replay_with_chat() -> ExtendedGameInformation {
+        let mut replay = ExtendedGameInformation::new();
+        replay.messages = vec![message("All", "glhf"), message("Team", "push left")];
+        replay.players = vec![ExtendedPlayerInformation {
+            steam_id: 76561198099396483,
+            ..Default::default()
+        }];
+        replay
+    }
+
+    #[test]
+    fn reports_mod_release_when_known() {
+        let mut replay = replay_with_chat();
+        let payload =
+            serde_json::to_value(ReplayReportDto::from(&replay, &PrivacySettings::default()))
+                .unwrap();
+        assert!(payload.get("mod_release").is_none());
+
+        replay.session.mod_version = "2.9.5".into();
+        let payload =
+            serde_json::to_value(ReplayReportDto::from(&replay, &PrivacySettings::default()))
+                .unwrap();
+        assert_eq!(payload["mod_release"], "2.9.5");
+    }
+
+    #[test]
+    fn reports_full_chat_by_default() {
+        let dto = ReplayReportDto::from(&replay_with_chat(), &PrivacySettings::default());
+
+        assert_eq!(dto.messages.len(), 2);
+        assert_eq!(dto.players[0].steam_id, 76561198099396483);
+        assert_eq!(dto.privacy.chat, ChatPolicy::All);
+    }
+
+    #[test]
+    fn withholds_team_chat() {
+        let dto = ReplayReportDto::from(
+            &replay_with_chat(),
+            &PrivacySettings {
+                chat: ChatPolicy::AllChatOnly,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(dto.messages.len(), 1);
+        assert_eq!(dto.messages[0].body, "glhf");
+    }
+
+    #[test]
+    fn redacts_message_bodies() {
+        let dto = ReplayReportDto::from(
+            &replay_with_chat(),
+            &PrivacySettings {
+                chat: ChatPolicy::Redacted,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(dto.messages.len(), 2);
+        assert!(dto
+            .messages
+            .iter()
+            .all(|message| message.body == REDACTED_MESSAGE_BODY));
+    }
+
+    #[test]
+    fn omits_chat_and_hashes_steam_ids() {
+        let dto = ReplayReportDto::from(
+            &replay_with_chat(),
+            &PrivacySettings {
+                chat: ChatPolicy::Omitted,
+                hash_steam_ids: true,
+                steam_id_hash_key: "shared secret".into(),
+                ..Default::default()
+            },
+        );
+
+        assert!(dto.messages.is_empty());
+        assert_eq!(
+            dto.players[0].steam_id,
+            hash_steam_id(76561198099396483, "shared secret")
+        );
+        assert_ne!(dto.players[0].steam_id, 76561198099396483);
+        assert!(dto.privacy.steam_ids_hashed);
+    }
+
+    #[test]
+    fn omits_leaver_timeline_on_request() {
+        let leaver = LeaverEvent {
+            kind: LeaveKind::Dropped,
+            at: "2022-11-16T17:21:56+01:00".into(),
+            frame: Some(8390),
+            game_time_secs: Some(839),
+            station: Some(2),
+            sim_id: Some(1000),
+            reason_code: None,
+        };
+        let mut replay = replay_with_chat();
+        replay.leaver_timeline = vec![leaver.clone()];
+        replay.first_leaver = Some(leaver);
+
+        let payload =
+            serde_json::to_value(ReplayReportDto::from(&replay, &PrivacySettings::default()))
+                .unwrap();
+        assert_eq!(payload["leaver_timeline"][0]["kind"], "dropped");
+        assert_eq!(payload["first_leaver"]["sim_id"], 1000);
+
+        let payload = serde_json::to_value(ReplayReportDto::from(
+            &replay,
+            &PrivacySettings {
+                omit_leaver_timeline: true,
+                ..Default::default()
+            },
+        ))
+        .unwrap();
+        assert!(payload.get("leaver_timeline").is_none());
+        assert!(payload.get("first_leaver").is_none());
+        assert_eq!(payload["privacy"]["leaver_timeline_omitted"], true);
+    }
+}
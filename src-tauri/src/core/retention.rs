@@ -0,0 +1,360 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use super::{
+    error::ParserAppResult, get_playback_dir, history::HistoryDatabase, playback,
+    settings::Settings,
+};
+
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RetentionSettings {
+    /// Run the pruner in the background. Previews are available regardless.
+    pub enabled: bool,
+    pub max_age_days: Option<u64>,
+    pub max_total_size_mb: Option<u64>,
+    pub keep_starred: bool,
+    /// Keep league games, see `LeagueRules`
+    pub keep_league: bool,
+    /// Prune replays of games the local player only observed
+    pub only_my_games: bool,
+    /// Also remove pruned games from the match history
+    pub delete_history: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum PruneReason {
+    TooOld,
+    NotMyGame,
+    SizeLimit,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PruneCandidate {
+    pub path: PathBuf,
+    pub relic_match_id: usize,
+    pub size: u64,
+    pub reason: PruneReason,
+    #[serde(skip)]
+    history_id: i64,
+}
+
+struct ReplayCopy {
+    path: PathBuf,
+    relic_match_id: usize,
+    size: u64,
+    played_at: SystemTime,
+    history_id: i64,
+    protected: bool,
+    my_game: bool,
+}
+
+/// Lists the replay copies in `playback_dir` that the retention rules would delete, without
+/// touching anything. Only copies the app wrote for a match in the history, named after
+/// `copy_name`, are considered. Replays the user saved themselves are never deleted.
+pub fn preview(
+    rules: &RetentionSettings,
+    history: &HistoryDatabase,
+    playback_dir: &Path,
+    copy_name: &str,
+) -> ParserAppResult<Vec<PruneCandidate>> {
+    let now = SystemTime::now();
+    let mut replays = replay_copies(rules, history, playback_dir, copy_name)?;
+    // Oldest first, so that the size limit removes the oldest replays
+    replays.sort_by_key(|replay| replay.played_at);
+
+    let mut candidates = vec![];
+    let mut remaining = vec![];
+
+    for replay in replays {
+        let age = now.duration_since(replay.played_at).unwrap_or_default();
+        let too_old = rules
+            .max_age_days
+            .is_some_and(|days| age.as_secs() > days * SECONDS_PER_DAY);
+
+        let reason = match (
+            replay.protected,
+            too_old,
+            rules.only_my_games && !replay.my_game,
+        ) {
+            (true, _, _) => None,
+            (false, true, _) => Some(PruneReason::TooOld),
+            (false, false, true) => Some(PruneReason::NotMyGame),
+            (false, false, false) => None,
+        };
+
+        match reason {
+            Some(reason) => candidates.push(candidate(&replay, reason)),
+            None => remaining.push(replay),
+        }
+    }
+
+    if let Some(max_total_size_mb) = rules.max_total_size_mb {
+        let max_total_size = max_total_size_mb * 1024 * 1024;
+        let mut total_size = remaining.iter().map(|replay| replay.size).sum::<u64>();
+
+        for replay in remaining.iter().filter(|replay| !replay.protected) {
+            if total_size <= max_total_size {
+                break;
+            }
+
+            total_size -= replay.size;
+            candidates.push(candidate(replay, PruneReason::SizeLimit));
+        }
+    }
+
+    Ok(candidates)
+}
+
+/// Deletes everything `preview` lists and returns what was deleted. Replays that can not be
+/// deleted are logged and skipped.
+pub fn prune(
+    rules: &RetentionSettings,
+    history: &HistoryDatabase,
+    playback_dir: &Path,
+    copy_name: &str,
+) -> ParserAppResult<Vec<PruneCandidate>> {
+    let mut pruned = vec![];
+
+    for candidate in preview(rules, history, playback_dir, copy_name)? {
+        if let Err(e) = fs::remove_file(&candidate.path) {
+            tracing::error!("Could not delete archived replay {:?}: {e}", candidate.path);
+            continue;
+        }
+
+        if rules.delete_history {
+            if let Err(e) = history.delete_match(candidate.history_id) {
+                tracing::error!(
+                    "Could not delete match {} from history: {e}",
+                    candidate.relic_match_id
+                );
+            }
+        }
+
+        pruned.push(candidate);
+    }
+
+    tracing::info!("Pruned {} archived replays", pruned.len());
+    Ok(pruned)
+}
+
+/// Applies the retention rules once an hour for as long as the app is running
+pub fn spawn_pruner(handle: AppHandle) {
+    thread::spawn(move || loop {
+        if let Err(e) = prune_with_settings(&handle) {
+            tracing::error!("Could not prune archived replays: {e}");
+        }
+
+        thread::sleep(PRUNE_INTERVAL);
+    });
+}
+
+fn prune_with_settings(handle: &AppHandle) -> ParserAppResult<()> {
+    let settings = Settings::load(handle)?;
+    if !settings.retention.enabled {
+        return Ok(());
+    }
+
+    let Some(history) = handle.try_state::<HistoryDatabase>() else {
+        return Ok(());
+    };
+
+    prune(
+        &settings.retention,
+        &history,
+        &get_playback_dir(handle, &settings)?,
+        &settings.paths.replay_copy_name,
+    )?;
+
+    Ok(())
+}
+
+/// The replay copies in `playback_dir` that belong to a match in the history
+fn replay_copies(
+    rules: &RetentionSettings,
+    history: &HistoryDatabase,
+    playback_dir: &Path,
+    copy_name: &str,
+) -> ParserAppResult<Vec<ReplayCopy>> {
+    let mut replays = vec![];
+    let mut seen = HashSet::new();
+
+    for record in history.list_matches()? {
+        let path = playback_dir.join(playback::replay_copy_name(
+            copy_name,
+            record.relic_match_id,
+            &record.map,
+        ));
+        // The same game may be stored more than once, e.g. with and without its replay md5
+        if !seen.insert(path.clone()) {
+            continue;
+        }
+
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+
+        let played_at = match DateTime::parse_from_rfc3339(&record.played_at_utc) {
+            Ok(played_at) => SystemTime::from(played_at),
+            Err(_) => metadata.modified()?,
+        };
+
+        let protected =
+            (rules.keep_starred && record.starred) || (rules.keep_league && record.league);
+        let my_game = record
+            .players
+            .iter()
+            .any(|player| player.steam_id != 0 && player.steam_id == record.local_steam_id);
+
+        replays.push(ReplayCopy {
+            path,
+            relic_match_id: record.relic_match_id,
+            size: metadata.len(),
+            played_at,
+            history_id: record.id,
+            protected,
+            my_game,
+        });
+    }
+
+    Ok(replays)
+}
+
+fn candidate(replay: &ReplayCopy, reason: PruneReason) -> PruneCandidate {
+    PruneCandidate {
+        path: replay.path.clone(),
+        relic_match_id: replay.relic_match_id,
+        size: replay.size,
+        reason,
+        history_id: replay.history_id,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{SecondsFormat, Utc};
+
+    use crate::core::{
+        history::MatchRecord,
+        playback::DEFAULT_REPLAY_COPY_NAME,
+        player_info::LogfilePlayerStatus,
+        test_support::{player, record, TestDir},
+    };
+
+    use super::*;
+
+    fn write_replay(playback_dir: &Path, file_name: &str) {
+        fs::write(playback_dir.join(file_name), vec![0u8; 1024 * 1024]).unwrap();
+    }
+
+    fn played(
+        relic_match_id: usize,
+        map: &str,
+        age_days: i64,
+        local_player_played: bool,
+    ) -> MatchRecord {
+        let played_at = Utc::now() - chrono::Duration::days(age_days);
+        let steam_id = if local_player_played { 1 } else { 2 };
+
+        MatchRecord {
+            map: map.into(),
+            played_at: played_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            local_steam_id: 1,
+            players: vec![player(steam_id, 0, LogfilePlayerStatus::Won)],
+            ..record(relic_match_id)
+        }
+    }
+
+    #[test]
+    fn previews_and_prunes_replays() {
+        let test_dir = TestDir::new("retention");
+        let playback_dir = test_dir.path();
+
+        for file_name in [
+            "temp.rec",
+            "1_6p_estia.rec",
+            "2_6p_estia.rec",
+            "3_6p_avensa.rec",
+            "4_6p_avensa.rec",
+            "5_6p_avensa.rec",
+            // Saved by the user, or not named like the copy of match 7
+            "6_6p_avensa.rec",
+            "7_6p_estia.rec",
+        ] {
+            write_replay(playback_dir, file_name);
+        }
+
+        let history = HistoryDatabase::open_in_memory().unwrap();
+        history
+            .save_record(&played(1, "6p_estia", 100, true))
+            .unwrap();
+        history
+            .save_record(&MatchRecord {
+                league: true,
+                ..played(2, "6p_estia", 90, true)
+            })
+            .unwrap();
+        history
+            .save_record(&played(3, "6p_avensa", 3, true))
+            .unwrap();
+        history
+            .save_record(&played(4, "6p_avensa", 2, false))
+            .unwrap();
+        history
+            .save_record(&played(5, "6p_avensa", 1, true))
+            .unwrap();
+        history
+            .save_record(&played(7, "6p_avensa", 100, true))
+            .unwrap();
+
+        let rules = RetentionSettings {
+            max_age_days: Some(30),
+            max_total_size_mb: Some(2),
+            keep_league: true,
+            only_my_games: true,
+            delete_history: true,
+            ..Default::default()
+        };
+
+        let candidates = preview(&rules, &history, playback_dir, DEFAULT_REPLAY_COPY_NAME).unwrap();
+        let reasons = candidates
+            .iter()
+            .map(|candidate| (candidate.relic_match_id, candidate.reason))
+            .collect::<Vec<_>>();
+
+        // 2 is a league game, 3 is the oldest replay above the size limit
+        assert_eq!(
+            reasons,
+            vec![
+                (1, PruneReason::TooOld),
+                (4, PruneReason::NotMyGame),
+                (3, PruneReason::SizeLimit),
+            ]
+        );
+        assert!(playback_dir.join("1_6p_estia.rec").exists());
+
+        let pruned = prune(&rules, &history, playback_dir, DEFAULT_REPLAY_COPY_NAME).unwrap();
+        assert_eq!(pruned.len(), 3);
+        assert!(!playback_dir.join("1_6p_estia.rec").exists());
+        assert!(playback_dir.join("2_6p_estia.rec").exists());
+        assert!(playback_dir.join("6_6p_avensa.rec").exists());
+        assert!(playback_dir.join("7_6p_estia.rec").exists());
+        assert!(playback_dir.join("temp.rec").exists());
+        assert!(history.match_by_relic_id(1).unwrap().is_none());
+    }
+}
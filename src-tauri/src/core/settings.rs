@@ -0,0 +1,310 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::{
+    discord::DiscordWebhookSink,
+    dry_run::DryRunSettings,
+    error::{ParserAppError, ParserAppResult, ResultExt},
+    league::LeagueRules,
+    logging::LoggingSettings,
+    playback::{self, DEFAULT_REPLAY_COPY_NAME},
+    privacy::PrivacySettings,
+    rating::RatingSettings,
+    retention::RetentionSettings,
+    transport::ReportSettings,
+};
+
+const SETTINGS_FILE_NAME: &str = "settings.json";
+/// Emitted with the new settings whenever they are saved. The file watcher reloads on it.
+pub const SETTINGS_CHANGED_EVENT: &str = "settings-changed";
+pub const SETTINGS_VERSION: u32 = 1;
+const DEFAULT_DEBOUNCE_SECS: u64 = 5;
+const MAX_DEBOUNCE_SECS: u64 = 300;
+
+/// Upgrades settings files of older versions one step at a time. Entry `n` turns a version `n`
+/// file into a version `n + 1` file.
+const MIGRATIONS: &[fn(&mut Value)] = &[
+    // Unversioned files were written before versioning was introduced and share the layout of
+    // version 1
+    |_| {},
+];
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub watcher: WatcherSettings,
+    pub paths: PathSettings,
+    pub report: ReportSettings,
+    pub league: LeagueRules,
+    pub dry_run: DryRunSettings,
+    pub privacy: PrivacySettings,
+    pub discord_webhooks: Vec<DiscordWebhookSink>,
+    pub rating: RatingSettings,
+    pub retention: RetentionSettings,
+    pub logging: LoggingSettings,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WatcherSettings {
+    /// Seconds `temp.rec` has to stay unchanged before a game is processed
+    pub debounce_secs: u64,
+}
+
+/// Overrides for installations outside of the default Documents folder
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PathSettings {
+    pub logfile: Option<PathBuf>,
+    pub playback_dir: Option<PathBuf>,
+    /// Template for the replay copies in the playback folder, see `playback::replay_copy_name`
+    pub replay_copy_name: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            watcher: WatcherSettings::default(),
+            paths: PathSettings::default(),
+            report: ReportSettings::default(),
+            league: LeagueRules::default(),
+            dry_run: DryRunSettings::default(),
+            privacy: PrivacySettings::default(),
+            discord_webhooks: vec![],
+            rating: RatingSettings::default(),
+            retention: RetentionSettings::default(),
+            logging: LoggingSettings::default(),
+        }
+    }
+}
+
+impl Default for PathSettings {
+    fn default() -> Self {
+        Self {
+            logfile: None,
+            playback_dir: None,
+            replay_copy_name: DEFAULT_REPLAY_COPY_NAME.into(),
+        }
+    }
+}
+
+impl Default for WatcherSettings {
+    fn default() -> Self {
+        Self {
+            debounce_secs: DEFAULT_DEBOUNCE_SECS,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(handle: &AppHandle) -> ParserAppResult<Self> {
+        let settings_path = settings_path(handle)?;
+        tracing::debug!("Settings path: {settings_path:?}");
+
+        Self::read_from(&settings_path)
+    }
+
+    pub fn read_from(settings_path: &Path) -> ParserAppResult<Self> {
+        // A fresh installation has no settings file yet - run with the defaults
+        if !settings_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(settings_path).with_path(settings_path)?;
+        let mut value: Value = serde_json::from_str(&content).with_path(settings_path)?;
+        migrate(&mut value).with_path(settings_path)?;
+
+        let settings: Self = serde_json::from_value(value).with_path(settings_path)?;
+        settings.validate().with_path(settings_path)?;
+
+        Ok(settings)
+    }
+
+    /// Validates and persists the settings, then tells the running app about them
+    pub fn save(&self, handle: &AppHandle) -> ParserAppResult<()> {
+        self.write_to(&settings_path(handle)?)?;
+        handle.emit(SETTINGS_CHANGED_EVENT, self)?;
+
+        Ok(())
+    }
+
+    pub fn write_to(&self, settings_path: &Path) -> ParserAppResult<()> {
+        self.validate()?;
+
+        let settings = Self {
+            version: SETTINGS_VERSION,
+            ..self.clone()
+        };
+
+        if let Some(parent) = settings_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(settings_path, serde_json::to_vec_pretty(&settings)?).with_path(settings_path)?;
+
+        Ok(())
+    }
+
+    /// Checks everything serde can not, reporting all problems at once
+    pub fn validate(&self) -> ParserAppResult<()> {
+        let mut problems = vec![];
+
+        if !(1..=MAX_DEBOUNCE_SECS).contains(&self.watcher.debounce_secs) {
+            problems.push(format!(
+                "watcher.debounce_secs must be between 1 and {MAX_DEBOUNCE_SECS}, got {}",
+                self.watcher.debounce_secs
+            ));
+        }
+
+        if let Err(e) = reqwest::Url::parse(&self.report.endpoint) {
+            problems.push(format!(
+                "report.endpoint {:?} is not a valid URL: {e}",
+                self.report.endpoint
+            ));
+        }
+
+        for problem in playback::template_problems(&self.paths.replay_copy_name) {
+            problems.push(format!(
+                "paths.replay_copy_name {:?} {problem}",
+                self.paths.replay_copy_name
+            ));
+        }
+
+        if self.report.timeout_secs == 0 {
+            problems.push("report.timeout_secs must be at least 1".into());
+        }
+
+        for pattern in self.league.game_name_patterns.iter() {
+            if let Err(e) = Regex::new(pattern) {
+                problems.push(format!(
+                    "league.game_name_patterns {pattern:?} is invalid: {e}"
+                ));
+            }
+        }
+
+        for (index, sink) in self.discord_webhooks.iter().enumerate() {
+            if reqwest::Url::parse(&sink.webhook_url).is_err() {
+                problems.push(format!(
+                    "discord_webhooks[{index}].webhook_url is not a valid URL"
+                ));
+            }
+        }
+
+        if self.privacy.hash_steam_ids && self.privacy.steam_id_hash_key.is_empty() {
+            problems.push("privacy.steam_id_hash_key is required to hash steam ids".into());
+        }
+
+        if self.rating.k_factor <= 0.0 {
+            problems.push("rating.k_factor must be positive".into());
+        }
+
+        if self.retention.max_age_days == Some(0) {
+            problems.push("retention.max_age_days must be at least 1".into());
+        }
+
+        if self.logging.max_files == 0 {
+            problems.push("logging.max_files must be at least 1".into());
+        }
+
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(ParserAppError::InvalidSettingsError(problems)),
+        }
+    }
+}
+
+fn settings_path(handle: &AppHandle) -> ParserAppResult<PathBuf> {
+    Ok(handle.path().app_config_dir()?.join(SETTINGS_FILE_NAME))
+}
+
+fn migrate(value: &mut Value) -> ParserAppResult<()> {
+    let Some(settings) = value.as_object_mut() else {
+        return Err(ParserAppError::InvalidSettingsError(vec![
+            "settings must be a JSON object".into(),
+        ]));
+    };
+
+    let version = match settings.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| {
+                ParserAppError::InvalidSettingsError(vec![format!("invalid version {version}")])
+            })?,
+    };
+
+    if version > SETTINGS_VERSION {
+        return Err(ParserAppError::InvalidSettingsError(vec![format!(
+            "settings version {version} is newer than the supported version {SETTINGS_VERSION}"
+        )]));
+    }
+
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        tracing::info!("Migrating settings from version {from} to {}", from + 1);
+        migration(value);
+    }
+
+    value["version"] = SETTINGS_VERSION.into();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::test_support::TestDir;
+
+    use super::*;
+
+    #[test]
+    fn migrates_unversioned_settings() {
+        let test_dir = TestDir::new("settings");
+        let settings_path = test_dir.join(SETTINGS_FILE_NAME);
+        fs::write(&settings_path, r#"{ "dry_run": { "enabled": true } }"#).unwrap();
+
+        let settings = Settings::read_from(&settings_path).unwrap();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert!(settings.dry_run.enabled);
+        assert_eq!(settings.watcher, WatcherSettings::default());
+
+        settings.write_to(&settings_path).unwrap();
+        let written: Value = serde_json::from_slice(&fs::read(&settings_path).unwrap()).unwrap();
+        assert_eq!(written["version"], SETTINGS_VERSION);
+    }
+
+    #[test]
+    fn reports_all_invalid_values() {
+        let test_dir = TestDir::new("settings");
+        let settings_path = test_dir.join(SETTINGS_FILE_NAME);
+        fs::write(
+            &settings_path,
+            r#"{
+                "watcher": { "debounce_secs": 0 },
+                "paths": { "replay_copy_name": "{map}.rec" },
+                "report": { "endpoint": "not a url" },
+                "league": { "game_name_patterns": ["[ESL"] }
+            }"#,
+        )
+        .unwrap();
+
+        let error = Settings::read_from(&settings_path).unwrap_err();
+        assert_eq!(error.code(), "invalid_settings");
+        assert_eq!(error.context().path, Some(settings_path.clone()));
+
+        let message = error.to_string();
+        assert!(message.contains("watcher.debounce_secs"));
+        assert!(message.contains("paths.replay_copy_name"));
+        assert!(message.contains("report.endpoint"));
+        assert!(message.contains("league.game_name_patterns"));
+
+        fs::write(&settings_path, r#"{ "version": 99 }"#).unwrap();
+        assert!(Settings::read_from(&settings_path).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use super::{
+    history::{MatchRecord, PlayerRecord},
+    player_info::{race_name, LogfilePlayerStatus},
+};
+
+#[derive(Debug, Default, Serialize)]
+pub struct PlayerStatistics {
+    pub steam_id: usize,
+    pub name: String,
+    pub record: WinRecord,
+    pub by_race: Vec<Breakdown>,
+    pub by_map: Vec<Breakdown>,
+    pub by_opponent_race: Vec<Breakdown>,
+    pub by_teammate: Vec<Breakdown>,
+    /// Positive for a running win streak, negative for a running loss streak
+    pub current_streak: i64,
+    pub longest_win_streak: usize,
+    pub longest_loss_streak: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct WinRecord {
+    pub games: usize,
+    pub wins: usize,
+    pub win_rate: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Breakdown {
+    pub key: String,
+    pub label: String,
+    #[serde(flatten)]
+    pub record: WinRecord,
+}
+
+impl WinRecord {
+    pub(crate) fn add(&mut self, won: bool) {
+        self.games += 1;
+        if won {
+            self.wins += 1;
+        }
+        self.win_rate = self.wins as f64 / self.games as f64;
+    }
+}
+
+/// Aggregates the given matches from the point of view of one player. The matches are expected
+/// in chronological order, as returned by `HistoryDatabase::matches_with_player`.
+pub fn player_statistics(steam_id: usize, matches: &[MatchRecord]) -> PlayerStatistics {
+    let mut statistics = PlayerStatistics {
+        steam_id,
+        ..Default::default()
+    };
+
+    let mut by_race: BTreeMap<usize, WinRecord> = BTreeMap::new();
+    let mut by_map: BTreeMap<String, WinRecord> = BTreeMap::new();
+    let mut by_opponent_race: BTreeMap<usize, WinRecord> = BTreeMap::new();
+    let mut by_teammate: BTreeMap<usize, (String, WinRecord)> = BTreeMap::new();
+
+    for record in matches.iter() {
+        let Some(player) = find_player(record, steam_id as u64) else {
+            continue;
+        };
+
+        let won = player.status == LogfilePlayerStatus::Won;
+        statistics.name = player.name.clone();
+        statistics.record.add(won);
+
+        by_race.entry(player.race).or_default().add(won);
+        by_map.entry(record.map.clone()).or_default().add(won);
+
+        for other in record
+            .players
+            .iter()
+            .filter(|other| other.steam_id != steam_id)
+        {
+            if other.team == player.team {
+                let (name, teammate) = by_teammate.entry(other.steam_id).or_default();
+                *name = other.name.clone();
+                teammate.add(won);
+            } else {
+                by_opponent_race.entry(other.race).or_default().add(won);
+            }
+        }
+
+        update_streaks(&mut statistics, won);
+    }
+
+    statistics.by_race = race_breakdown(by_race);
+    statistics.by_map = by_map
+        .into_iter()
+        .map(|(map, record)| Breakdown {
+            key: map.clone(),
+            label: map,
+            record,
+        })
+        .collect();
+    statistics.by_opponent_race = race_breakdown(by_opponent_race);
+    statistics.by_teammate = by_teammate
+        .into_iter()
+        .map(|(steam_id, (name, record))| Breakdown {
+            key: steam_id.to_string(),
+            label: name,
+            record,
+        })
+        .collect();
+
+    statistics
+}
+
+/// The player of the match with the given steam id or relic id. Unknown ids are stored as 0 and
+/// never match.
+pub(crate) fn find_player(record: &MatchRecord, id: u64) -> Option<&PlayerRecord> {
+    record
+        .players
+        .iter()
+        .find(|player| id != 0 && (player.steam_id as u64 == id || player.relic_id == id))
+}
+
+fn update_streaks(statistics: &mut PlayerStatistics, won: bool) {
+    statistics.current_streak = match (won, statistics.current_streak) {
+        (true, streak) if streak > 0 => streak + 1,
+        (true, _) => 1,
+        (false, streak) if streak < 0 => streak - 1,
+        (false, _) => -1,
+    };
+
+    let streak = statistics.current_streak.unsigned_abs() as usize;
+    if won {
+        statistics.longest_win_streak = statistics.longest_win_streak.max(streak);
+    } else {
+        statistics.longest_loss_streak = statistics.longest_loss_streak.max(streak);
+    }
+}
+
+fn race_breakdown(races: BTreeMap<usize, WinRecord>) -> Vec<Breakdown> {
+    races
+        .into_iter()
+        .map(|(race, record)| Breakdown {
+            key: race.to_string(),
+            label: race_name(race),
+            record,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::test_support::player;
+
+    use super::*;
+
+    fn game(map: &str, local_won: bool) -> MatchRecord {
+        let status = |won: bool| match won {
+            true => LogfilePlayerStatus::Won,
+            false => LogfilePlayerStatus::Conceded,
+        };
+
+        MatchRecord {
+            map: map.into(),
+            players: vec![
+                PlayerRecord {
+                    race: 2,
+                    ..player(1, 0, status(local_won))
+                },
+                PlayerRecord {
+                    race: 0,
+                    ..player(2, 0, status(local_won))
+                },
+                PlayerRecord {
+                    race: 4,
+                    ..player(3, 1, status(!local_won))
+                },
+                PlayerRecord {
+                    race: 5,
+                    ..player(4, 1, status(!local_won))
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn aggregates_win_rates_and_streaks() {
+        let matches = vec![
+            game("6p_estia", true),
+            game("6p_estia", false),
+            game("6p_avensa", true),
+            game("6p_estia", true),
+        ];
+
+        let statistics = player_statistics(1, &matches);
+
+        assert_eq!(statistics.record.games, 4);
+        assert_eq!(statistics.record.wins, 3);
+        assert_eq!(statistics.record.win_rate, 0.75);
+
+        assert_eq!(statistics.by_race.len(), 1);
+        assert_eq!(statistics.by_race[0].label, "Orks");
+
+        let estia = statistics
+            .by_map
+            .iter()
+            .find(|breakdown| breakdown.key == "6p_estia")
+            .unwrap();
+        assert_eq!(estia.record.games, 3);
+        assert_eq!(estia.record.wins, 2);
+
+        assert_eq!(statistics.by_opponent_race.len(), 2);
+        assert_eq!(statistics.by_opponent_race[0].record.games, 4);
+
+        assert_eq!(statistics.by_teammate.len(), 1);
+        assert_eq!(statistics.by_teammate[0].label, "Player 2");
+
+        assert_eq!(statistics.current_streak, 2);
+        assert_eq!(statistics.longest_win_streak, 2);
+        assert_eq!(statistics.longest_loss_streak, 1);
+    }
+}
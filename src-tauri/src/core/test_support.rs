@@ -0,0 +1,91 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use super::{
+    game::ExtendedGameInformation,
+    history::{MatchRecord, PlayerRecord},
+    player_info::{ExtendedPlayerInformation, LogfilePlayerStatus},
+};
+
+static NEXT_TEST_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// Empty directory in the system temp dir that is removed again when dropped. Every directory
+/// gets its own name, so tests running in parallel never share one.
+pub struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "replay-parser-gui-{name}-{}-{}",
+            std::process::id(),
+            NEXT_TEST_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.path.join(path)
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Processed 1v1 on Estia that Alice (steam id 1) won against Bob (steam id 2)
+pub fn game(id: usize) -> ExtendedGameInformation {
+    let mut game = ExtendedGameInformation::new();
+    game.id = id;
+    game.md5 = format!("md5-{id}");
+    game.map.path = "DATA:maps\\pvp\\6p_estia".into();
+    game.players = vec![
+        ExtendedPlayerInformation {
+            steam_id: 1,
+            name: "Alice".into(),
+            status: LogfilePlayerStatus::Won,
+            ..Default::default()
+        },
+        ExtendedPlayerInformation {
+            steam_id: 2,
+            name: "Bob".into(),
+            team: 1,
+            status: LogfilePlayerStatus::Conceded,
+            ..Default::default()
+        },
+    ];
+    game
+}
+
+/// Stored match on Estia. Every relic match id gets its own md5, so several of them can be saved.
+pub fn record(relic_match_id: usize) -> MatchRecord {
+    MatchRecord {
+        relic_match_id,
+        md5: format!("md5-{relic_match_id}"),
+        map: "6p_estia".into(),
+        ..Default::default()
+    }
+}
+
+pub fn player(steam_id: usize, team: u32, status: LogfilePlayerStatus) -> PlayerRecord {
+    PlayerRecord {
+        steam_id,
+        name: format!("Player {steam_id}"),
+        team,
+        status,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::error::{ParserAppError, ParserAppResult};
+
+pub const ESL_REPORT_ENDPOINT: &str = "http://dawnofwar.info/esl/esl-report.php";
+pub const REPORT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReportSettings {
+    pub endpoint: String,
+    pub timeout_secs: u64,
+    /// Players are warned when their client runs a different mod version, e.g. `2.9.5`. With
+    /// `None` the check is skipped and the log says so for every game.
+    pub expected_mod_version: Option<String>,
+    /// Games against computer opponents are kept in the history but not sent to the ESL server
+    pub skip_computer_games: bool,
+}
+
+impl Default for ReportSettings {
+    fn default() -> Self {
+        Self {
+            endpoint: ESL_REPORT_ENDPOINT.into(),
+            timeout_secs: REPORT_TIMEOUT.as_secs(),
+            expected_mod_version: None,
+            skip_computer_games: false,
+        }
+    }
+}
+
+impl ReportSettings {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+/// Delivers a serialized report to the ESL server and hands back the raw response body
+pub trait ReportTransport {
+    fn send_report(&self, payload: &Value) -> ParserAppResult<String>;
+}
+
+pub struct HttpReportTransport {
+    client: reqwest::blocking::Client,
+    endpoint: String,
+}
+
+impl HttpReportTransport {
+    pub fn new(endpoint: &str, timeout: Duration) -> ParserAppResult<Self> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(timeout)
+            .build()?;
+
+        Ok(Self {
+            client,
+            endpoint: endpoint.into(),
+        })
+    }
+}
+
+impl ReportTransport for HttpReportTransport {
+    fn send_report(&self, payload: &Value) -> ParserAppResult<String> {
+        let response = self.client.post(&self.endpoint).json(payload).send()?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ParserAppError::HttpStatusError {
+                service: "ESL server".into(),
+                status: status.as_u16(),
+            });
+        }
+
+        Ok(response.text()?)
+    }
+}
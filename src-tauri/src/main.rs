@@ -8,13 +8,15 @@ extern crate lazy_static;
 
 mod core;
 
-use crate::core::handle_new_game_event;
-use tracing::Level;
-use tracing_subscriber;
+use crate::core::{
+    handle_new_game_event,
+    history::setup_history_database,
+    logging::{setup_logging, setup_stdout_logging},
+    retention,
+};
 
 fn main() {
     color_eyre::install().unwrap();
-    tracing_subscriber::fmt().with_max_level(Level::INFO).init();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_store::Builder::new().build())
@@ -28,12 +30,54 @@ fn main() {
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_shell::init())
+        .invoke_handler(tauri::generate_handler![
+            crate::core::commands::submit_dry_run_report,
+            crate::core::commands::get_match_history,
+            crate::core::commands::get_match,
+            crate::core::commands::get_local_profiles,
+            crate::core::commands::get_player_statistics,
+            crate::core::commands::get_head_to_head,
+            crate::core::commands::get_ratings,
+            crate::core::commands::get_player_rating,
+            crate::core::commands::search_chat,
+            crate::core::commands::export_history,
+            crate::core::commands::import_history,
+            crate::core::commands::preview_retention,
+            crate::core::commands::apply_retention,
+            crate::core::commands::set_match_starred,
+            crate::core::commands::set_match_notes,
+            crate::core::commands::set_match_tags,
+            crate::core::commands::get_tags,
+            crate::core::commands::get_annotated_matches,
+            crate::core::commands::get_recent_log_lines,
+            crate::core::commands::create_diagnostics_bundle,
+            crate::core::commands::get_quarantined_games,
+            crate::core::commands::reprocess_quarantined_game,
+            crate::core::commands::delete_quarantined_game,
+            crate::core::commands::get_settings,
+            crate::core::commands::set_settings,
+        ])
         .setup(|app| {
             let handle = app.handle().clone();
 
+            if let Err(e) = setup_logging(&handle) {
+                setup_stdout_logging();
+                tracing::error!("Could not set up logging to a file, logging to stdout only: {e}");
+            }
+
+            if let Err(e) = setup_history_database(&handle) {
+                tracing::error!("Could not open match history database: {e}");
+            }
+
+            retention::spawn_pruner(handle.clone());
+
             std::thread::spawn(move || match handle_new_game_event(&handle) {
                 Ok(_) => (),
-                Err(e) => tracing::error!("Error occurred in app backend: {e}"),
+                Err(e) => tracing::error!(
+                    code = e.code(),
+                    context = ?e.context(),
+                    "Error occurred in app backend: {e}"
+                ),
             });
 
             Ok(())
@@ -38,6 +38,17 @@ export class TauriService {
       });
     });
 
+    appWindow.listen<ReportStatus>('report-status', (event) => {
+      const { id, status } = event.payload;
+
+      this.matches_state.update(state => {
+        const matches = state.map(match => match.match_id === id ? { ...match, status: parseStatus(status) } : match);
+        this.json_store()?.set('matches', matches);
+
+        return matches;
+      });
+    });
+
     load('store.json')
       .then(store => {
         this.json_store.set(store);
@@ -54,20 +65,27 @@ export class TauriService {
   }
 }
 
-function mapJsonToVM(json: ReplayInfo): MatchItem {
-  let status;
+interface ReportStatus {
+  id: number;
+  status: string;
+  sent: boolean;
+}
+
+function parseStatus(status: string): Record<string, string> {
   try {
-    status = JSON.parse(json.status);
+    return JSON.parse(status);
   } catch (error) {
-    status = { error: json.status }
+    return { error: status };
   }
+}
 
+function mapJsonToVM(json: ReplayInfo): MatchItem {
   return {
     match_id: json.id,
     players: json.players,
     map: json.map,
     duration: ticks2time(json.ticks),
-    status,
+    status: parseStatus(json.status),
     played_at: json.date,
     messages: json.messages,
     actions: json.actions